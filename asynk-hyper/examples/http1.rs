@@ -7,8 +7,8 @@ use std::convert::Infallible;
 const SERVER_SOCK_ADDR: &str = "127.0.0.1:8040";
 
 fn main() {
-    asynk::builder().build().unwrap();
-    asynk::block_on(server()).unwrap();
+    let rt = asynk::builder().build().unwrap();
+    rt.block_on(server()).unwrap();
 }
 
 async fn server() {
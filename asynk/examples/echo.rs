@@ -1,13 +1,13 @@
-use asynk::net::TcpListener;
+use asynk::{net::TcpListener, JoinSet};
 use futures::{AsyncReadExt, AsyncWriteExt, StreamExt};
 use std::io::{self, Error};
 
 const SERVER_SOCK_ADDR: &str = "127.0.0.1:8040";
 
 fn main() {
-    asynk::builder().build().unwrap();
+    let rt = asynk::builder().build().unwrap();
 
-    asynk::block_on(async {
+    rt.block_on(async {
         let server = asynk::spawn(server());
         server.await.unwrap().unwrap();
     })
@@ -20,9 +20,15 @@ async fn server() -> io::Result<()> {
     let listener = TcpListener::bind(addr)?;
     let mut accept = listener.accept()?;
 
+    // Connection tasks are aborted when the server exits
+    let mut connections = JoinSet::new();
+
     while let Some(res) = accept.next().await {
+        // Forget the closed connections
+        while connections.try_join_next().is_some() {}
+
         // Spawn new task for the connection
-        asynk::spawn(async move {
+        connections.spawn(async move {
             // Accept the connection
             let (mut stream, _) = res?;
 
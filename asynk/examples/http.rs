@@ -13,9 +13,9 @@ Content-Length: 23
 ";
 
 fn main() {
-    asynk::builder().build().unwrap();
+    let rt = asynk::builder().build().unwrap();
 
-    asynk::block_on(async {
+    rt.block_on(async {
         let server = asynk::spawn(server());
         server.await.unwrap().unwrap()
     })
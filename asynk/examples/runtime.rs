@@ -9,8 +9,8 @@ use std::{
 };
 
 fn main() {
-    asynk::builder().build().unwrap();
-    asynk::block_on(main_future()).unwrap();
+    let rt = asynk::builder().build().unwrap();
+    rt.block_on(main_future()).unwrap();
 }
 
 async fn main_future() {
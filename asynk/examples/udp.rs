@@ -5,9 +5,9 @@ use asynk::net::UdpSocket;
 const SERVER_SOCK_ADDR: &str = "127.0.0.1:8040";
 
 fn main() {
-    asynk::builder().build().unwrap();
+    let rt = asynk::builder().build().unwrap();
 
-    asynk::block_on(async {
+    rt.block_on(async {
         let server = asynk::spawn(server());
         server.await.unwrap().unwrap();
     })
@@ -1,12 +1,36 @@
-use crate::{reactor::Reactor, Executor};
-use std::{io, num::NonZeroUsize};
-use tpool::ThreadPool;
-use zeet::WorkStealThreadPool;
+use crate::{
+    executor::{BlockingPool, Executor, Flavor, PoolKind, ThreadConfig},
+    reactor::Reactor,
+    Runtime, ThreadPerCore,
+};
+use core_affinity::CoreId;
+use std::{
+    io,
+    num::{NonZeroU32, NonZeroUsize},
+    sync::Arc,
+    time::Duration,
+};
+
+/// Default max number of blocking threads
+const DEFAULT_BLOCKING_THREADS: usize = 512;
+
+/// Default max number of polls of higher priority tasks while a lower priority
+/// has runnable tasks
+const DEFAULT_PRIORITY_AGING: u32 = 32;
+
+/// Default time after which an idle blocking thread exits
+const DEFAULT_KEEP_ALIVE: Duration = Duration::from_secs(10);
 
 #[derive(Default)]
 pub struct AsynkBuilder {
     task_threads: Option<NonZeroUsize>,
     blocking_threads: Option<NonZeroUsize>,
+    max_blocking_queue: Option<usize>,
+    compute_threads: Option<NonZeroUsize>,
+    thread_keep_alive: Option<Duration>,
+    priority_aging: Option<NonZeroU32>,
+    threads: ThreadConfig,
+    current_thread: bool,
 }
 
 impl AsynkBuilder {
@@ -19,27 +43,187 @@ impl AsynkBuilder {
         self
     }
 
+    /// Max number of blocking threads. Threads are started when all of them
+    /// are busy and exit after being idle for `thread_keep_alive`. 512 by
+    /// default.
     pub fn blocking_threads(mut self, val: NonZeroUsize) -> Self {
         self.blocking_threads = Some(val);
         self
     }
 
-    pub fn build(self) -> io::Result<()> {
+    /// Max number of blocking jobs waiting for a free thread. When the queue is
+    /// full, `try_spawn_blocking` fails and jobs of `spawn_blocking` are
+    /// rejected with `JoinError::QueueFull`. Unbounded by default.
+    pub fn max_blocking_queue(mut self, val: usize) -> Self {
+        self.max_blocking_queue = Some(val);
+        self
+    }
+
+    /// Max number of threads running the CPU-bound jobs of `spawn_compute`,
+    /// the number of CPUs by default. Threads are started on demand and exit
+    /// after being idle for `thread_keep_alive`.
+    pub fn compute_threads(mut self, val: NonZeroUsize) -> Self {
+        self.compute_threads = Some(val);
+        self
+    }
+
+    /// Time after which an idle blocking or compute thread exits, 10 seconds by default
+    pub fn thread_keep_alive(mut self, val: Duration) -> Self {
+        self.thread_keep_alive = Some(val);
+        self
+    }
+
+    /// Max number of tasks of higher priorities polled in a row by a worker
+    /// while tasks of a lower priority are runnable, 32 by default. After
+    /// that the lower priority gets a turn, so it isn't starved under load.
+    pub fn priority_aging(mut self, val: NonZeroU32) -> Self {
+        self.priority_aging = Some(val);
+        self
+    }
+
+    /// Prefix of the names of the runtime threads, `asynk` by default. Threads
+    /// are named `{prefix}-worker-{n}`, `{prefix}-blocking-{n}`,
+    /// `{prefix}-compute-{n}` and `{prefix}-core-{n}` for the threads of a
+    /// thread-per-core runtime.
+    pub fn thread_name_prefix(mut self, val: impl Into<String>) -> Self {
+        self.threads.name_prefix = Some(val.into());
+        self
+    }
+
+    /// Stack size of the runtime threads in bytes
+    pub fn thread_stack_size(mut self, val: usize) -> Self {
+        self.threads.stack_size = Some(val);
+        self
+    }
+
+    /// Callback run by every runtime thread when it starts, e.g. to set up
+    /// thread-local state
+    pub fn on_thread_start(mut self, f: impl Fn() + Send + Sync + 'static) -> Self {
+        self.threads.on_start = Some(Arc::new(f));
+        self
+    }
+
+    /// Callback run by every runtime thread before it exits
+    pub fn on_thread_stop(mut self, f: impl Fn() + Send + Sync + 'static) -> Self {
+        self.threads.on_stop = Some(Arc::new(f));
+        self
+    }
+
+    /// Callback run by a worker before it waits for new tasks or I/O events.
+    /// On a current-thread runtime it's run by the thread driving the runtime.
+    pub fn on_thread_park(mut self, f: impl Fn() + Send + Sync + 'static) -> Self {
+        self.threads.on_park = Some(Arc::new(f));
+        self
+    }
+
+    /// Callback run by a worker when it wakes up after waiting for new tasks
+    /// or I/O events
+    pub fn on_thread_unpark(mut self, f: impl Fn() + Send + Sync + 'static) -> Self {
+        self.threads.on_unpark = Some(Arc::new(f));
+        self
+    }
+
+    /// Ids of the CPU cores which the task workers are pinned to, in turn. Not
+    /// pinned by default. The threads of a thread-per-core runtime are pinned
+    /// to these cores instead of all cores in order.
+    pub fn task_affinity(mut self, cores: impl IntoIterator<Item = usize>) -> Self {
+        self.threads.task_affinity = cores.into_iter().map(|id| CoreId { id }).collect();
+        self
+    }
+
+    /// Ids of the CPU cores which the blocking and compute threads are pinned
+    /// to, in turn. Not pinned by default.
+    pub fn blocking_affinity(mut self, cores: impl IntoIterator<Item = usize>) -> Self {
+        self.threads.blocking_affinity = cores.into_iter().map(|id| CoreId { id }).collect();
+        self
+    }
+
+    /// Poll tasks and I/O events on the thread which calls `block_on`, without
+    /// worker and reactor threads. Spawned tasks make progress only while some
+    /// thread is blocked on the runtime. The task threads setting is ignored.
+    pub fn current_thread(mut self) -> Self {
+        self.current_thread = true;
+        self
+    }
+
+    pub fn build(self) -> io::Result<Runtime> {
+        if self.current_thread {
+            return self.build_current_thread();
+        }
+
         let task_threads = self.task_threads.unwrap_or_else(Self::default_thread_count);
 
-        let task_tp = WorkStealThreadPool::builder()
-            .max_threads(task_threads)
-            .build();
+        let exec = Executor::start(
+            Flavor::MultiThread(task_threads),
+            self.blocking_pool(),
+            self.compute_pool(),
+            Reactor::new()?,
+            self.aging(),
+            self.threads.clone(),
+        )?;
+
+        Ok(Runtime::new(exec))
+    }
+
+    /// Build a runtime per core. The task threads setting is the number of
+    /// cores (all cores by default), the blocking and compute threads settings
+    /// apply to every core. The core threads are pinned to the task affinity
+    /// cores, if set, and get the name prefix, stack size and start and stop
+    /// callbacks of the thread settings.
+    pub fn build_thread_per_core(self) -> io::Result<ThreadPerCore> {
+        let cores = self.task_threads.map_or_else(
+            || core_affinity::get_core_ids().map_or(0, |ids| ids.len()),
+            NonZeroUsize::get,
+        );
+
+        let cores = NonZeroUsize::new(cores).unwrap_or_else(Self::default_thread_count);
+        ThreadPerCore::new(cores.get(), self.threads.clone(), || {
+            self.build_current_thread()
+        })
+    }
+
+    fn build_current_thread(&self) -> io::Result<Runtime> {
+        let exec = Executor::start(
+            Flavor::CurrentThread,
+            self.blocking_pool(),
+            self.compute_pool(),
+            Reactor::new()?,
+            self.aging(),
+            self.threads.clone(),
+        )?;
 
-        let blocking_threads = self
+        Ok(Runtime::new(exec))
+    }
+
+    fn blocking_pool(&self) -> BlockingPool {
+        let threads = self
             .blocking_threads
+            .unwrap_or(NonZeroUsize::new(DEFAULT_BLOCKING_THREADS).expect("default is not zero"));
+
+        BlockingPool::new(
+            PoolKind::Blocking,
+            threads,
+            self.max_blocking_queue,
+            self.thread_keep_alive.unwrap_or(DEFAULT_KEEP_ALIVE),
+        )
+    }
+
+    fn compute_pool(&self) -> BlockingPool {
+        let threads = self
+            .compute_threads
             .unwrap_or_else(Self::default_thread_count);
 
-        let blocking_tp = ThreadPool::new(blocking_threads);
+        BlockingPool::new(
+            PoolKind::Compute,
+            threads,
+            None,
+            self.thread_keep_alive.unwrap_or(DEFAULT_KEEP_ALIVE),
+        )
+    }
 
-        Executor::new(task_tp, blocking_tp).set_global();
-        Reactor::new()?.set_global();
-        Ok(())
+    fn aging(&self) -> NonZeroU32 {
+        self.priority_aging
+            .unwrap_or(NonZeroU32::new(DEFAULT_PRIORITY_AGING).expect("default is not zero"))
     }
 
     fn default_thread_count() -> NonZeroUsize {
@@ -0,0 +1,54 @@
+use crate::task::Priority;
+use std::num::NonZeroU32;
+
+/// Picks the class of the next task to poll. Higher classes go first, but a
+/// class which had runnable tasks while `max` tasks of higher classes were
+/// polled goes before them.
+pub(crate) struct Aging {
+    max: u32,
+    /// Number of polls of higher classes while the class had runnable tasks
+    skipped: [u32; 3],
+}
+
+impl Aging {
+    pub(crate) fn new(max: NonZeroU32) -> Self {
+        Self {
+            max: max.get(),
+            skipped: [0; 3],
+        }
+    }
+
+    /// Classes in the order to look for the next task: the starving ones from
+    /// the lowest, then the others from the highest
+    pub(crate) fn order(&self) -> [Priority; 3] {
+        let mut order = Priority::ALL;
+
+        order.sort_by_key(|p| {
+            let index = p.index();
+
+            if self.skipped[index] >= self.max {
+                (0, usize::MAX - index)
+            } else {
+                (1, index)
+            }
+        });
+
+        order
+    }
+
+    /// Record the poll of a task of the class. `runnable` tells which classes
+    /// have runnable tasks.
+    pub(crate) fn polled(&mut self, class: Priority, runnable: [bool; 3]) {
+        self.skipped[class.index()] = 0;
+
+        let lower = class.index() + 1;
+
+        for (skipped, runnable) in self.skipped[lower..].iter_mut().zip(&runnable[lower..]) {
+            if *runnable {
+                *skipped += 1;
+            } else {
+                *skipped = 0;
+            }
+        }
+    }
+}
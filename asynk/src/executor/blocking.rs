@@ -0,0 +1,283 @@
+use super::{task::RawTask, threads, Executor, JoinError};
+use crate::{runtime::context, PoolMetrics};
+use parking_lot::{Condvar, Mutex, MutexGuard};
+use std::{
+    collections::VecDeque,
+    fmt,
+    future::Future,
+    io, mem,
+    num::NonZeroUsize,
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
+};
+
+/// Error returned by `try_spawn_blocking` when the queue of the blocking pool
+/// is full
+#[derive(Debug, thiserror::Error)]
+#[error("blocking queue is full")]
+pub struct QueueFullError;
+
+/// Kind of the jobs run by a pool of synchronous threads
+#[derive(Clone, Copy)]
+pub(crate) enum PoolKind {
+    /// Jobs waiting for I/O or other threads
+    Blocking,
+    /// CPU-bound jobs
+    Compute,
+}
+
+/// Future running the synchronous job on the first poll
+pub(crate) struct BlockingTask<F>(Option<F>);
+
+/// Thread pool running the synchronous jobs. Threads are started on demand, up
+/// to the limit, and exit after being idle for the keep-alive duration.
+pub(crate) struct BlockingPool {
+    kind: PoolKind,
+    inner: Mutex<Inner>,
+    /// Notified when a job is queued or the pool is shut down
+    cvar: Condvar,
+    /// Notified when a thread exits
+    exited: Condvar,
+    max_threads: usize,
+    /// Max number of jobs waiting for a free thread, unbounded if `None`
+    max_queue: Option<usize>,
+    /// Time after which an idle thread exits
+    keep_alive: Duration,
+}
+
+struct Inner {
+    queue: VecDeque<RawTask>,
+    /// Number of running threads
+    threads: usize,
+    /// Number of threads waiting for a job, which are not notified
+    idle: usize,
+    /// Number of notifications which are not taken by the idle threads yet
+    notified: usize,
+    /// Number of threads which are notified or started to take a queued job,
+    /// but haven't taken it yet
+    assigned: usize,
+    shutdown: bool,
+    handles: Vec<JoinHandle<()>>,
+}
+
+impl<F> BlockingTask<F> {
+    pub(crate) fn new(f: F) -> Self {
+        Self(Some(f))
+    }
+}
+
+impl<F> Unpin for BlockingTask<F> {}
+
+impl<F, T> Future for BlockingTask<F>
+where
+    F: FnOnce() -> T,
+{
+    type Output = T;
+
+    fn poll(mut self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<Self::Output> {
+        let f = self
+            .0
+            .take()
+            .expect("blocking task polled after completion");
+        Poll::Ready(f())
+    }
+}
+
+impl fmt::Display for PoolKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Blocking => f.write_str("blocking"),
+            Self::Compute => f.write_str("compute"),
+        }
+    }
+}
+
+impl BlockingPool {
+    pub(crate) fn new(
+        kind: PoolKind,
+        max_threads: NonZeroUsize,
+        max_queue: Option<usize>,
+        keep_alive: Duration,
+    ) -> Self {
+        Self {
+            kind,
+            inner: Mutex::new(Inner {
+                queue: VecDeque::new(),
+                threads: 0,
+                idle: 0,
+                notified: 0,
+                assigned: 0,
+                shutdown: false,
+                handles: Vec::new(),
+            }),
+            cvar: Condvar::new(),
+            exited: Condvar::new(),
+            max_threads: max_threads.get(),
+            max_queue,
+            keep_alive,
+        }
+    }
+
+    /// Queue the scheduled job, starting a new thread if all threads are busy.
+    /// The job is returned back with the error to reject it with, if the queue
+    /// is full or there is no thread to run it.
+    pub(crate) fn push(
+        &self,
+        task: RawTask,
+        exec: &Arc<Executor>,
+    ) -> Result<(), (RawTask, JoinError)> {
+        let mut inner = self.inner.lock();
+
+        if inner.shutdown {
+            // Nobody will run the job, it's cancelled by the runtime shutdown
+            return Ok(());
+        }
+
+        // Jobs which are not taken by notified or new threads yet
+        let waiting = inner.queue.len().saturating_sub(inner.assigned);
+        let free = inner.idle > 0 || inner.threads < self.max_threads;
+
+        if !free && self.max_queue.is_some_and(|max| waiting >= max) {
+            return Err((task, JoinError::QueueFull));
+        }
+
+        // The notified or new thread takes the job after the lock is released
+        if inner.idle > 0 {
+            inner.idle -= 1;
+            inner.notified += 1;
+            inner.assigned += 1;
+            self.cvar.notify_one();
+        } else if inner.threads < self.max_threads {
+            if let Err(e) = self.spawn_thread(&mut inner, exec) {
+                // Otherwise the job waits for a running thread
+                if inner.threads == 0 {
+                    return Err((task, JoinError::Spawn(e)));
+                }
+            }
+        }
+
+        inner.queue.push_back(task);
+
+        Ok(())
+    }
+
+    pub(crate) fn metrics(&self) -> PoolMetrics {
+        let inner = self.inner.lock();
+
+        PoolMetrics {
+            threads: inner.threads,
+            busy_threads: inner.threads.saturating_sub(inner.idle),
+            queue_depth: inner.queue.len(),
+        }
+    }
+
+    /// Stop the threads after they finish their current jobs, dropping the
+    /// queued ones. Waits for the threads until the deadline, if any. The
+    /// current thread isn't waited for, if it's one of the pool threads.
+    pub(crate) fn shutdown(&self, deadline: Option<Instant>) {
+        let mut inner = self.inner.lock();
+
+        inner.shutdown = true;
+        self.cvar.notify_all();
+
+        let queue = mem::take(&mut inner.queue);
+        let handles = mem::take(&mut inner.handles);
+
+        // Queued jobs may be dropped only without the lock
+        drop(inner);
+        drop(queue);
+
+        threads::join_all(
+            handles,
+            &self.inner,
+            &self.exited,
+            |inner| inner.threads,
+            deadline,
+        );
+    }
+
+    fn spawn_thread(&self, inner: &mut Inner, exec: &Arc<Executor>) -> io::Result<()> {
+        let kind = self.kind;
+
+        let handle = exec.threads.spawn(kind.into(), {
+            let exec = Arc::clone(exec);
+
+            move || {
+                let _enter = context::enter(Arc::clone(&exec));
+                exec.pool(kind).run();
+            }
+        })?;
+
+        inner.threads += 1;
+        inner.assigned += 1;
+        inner.handles.push(handle);
+
+        Ok(())
+    }
+
+    /// Run the queued jobs until shutdown or until the thread is idle for too
+    /// long
+    fn run(&self) {
+        let mut inner = self.inner.lock();
+
+        // The thread is started to take a queued job
+        let mut assigned = true;
+
+        loop {
+            if mem::take(&mut assigned) {
+                inner.assigned -= 1;
+            }
+
+            if let Some(task) = inner.queue.pop_front() {
+                drop(inner);
+                task.run();
+                inner = self.inner.lock();
+                continue;
+            }
+
+            if inner.shutdown || !self.wait(&mut inner) {
+                break;
+            }
+
+            assigned = true;
+        }
+
+        inner.threads -= 1;
+        self.exited.notify_all();
+    }
+
+    /// Wait for a job as an idle thread. Returns `false` if the thread must
+    /// exit, because the pool is shut down or the keep-alive time is elapsed.
+    fn wait(&self, inner: &mut MutexGuard<'_, Inner>) -> bool {
+        inner.idle += 1;
+
+        loop {
+            let timed_out = self.cvar.wait_for(inner, self.keep_alive).timed_out();
+
+            // The notification may be taken by a thread which has timed out
+            // meanwhile, as they are interchangeable
+            if inner.notified > 0 {
+                inner.notified -= 1;
+                return true;
+            }
+
+            if inner.shutdown {
+                inner.idle -= 1;
+                return false;
+            }
+
+            if timed_out {
+                inner.idle -= 1;
+
+                // The retired thread is detached
+                let current = thread::current().id();
+                inner.handles.retain(|h| h.thread().id() != current);
+
+                return false;
+            }
+        }
+    }
+}
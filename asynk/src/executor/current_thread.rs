@@ -0,0 +1,226 @@
+use super::{aging::Aging, steal, task::RawTask, ThreadConfig, EVENT_INTERVAL};
+use crate::{reactor::Reactor, RuntimeMetrics};
+use crossbeam_deque::Injector;
+use parking_lot::Mutex;
+use std::{
+    num::NonZeroU32,
+    sync::atomic::{self, AtomicBool, AtomicU64, Ordering},
+    thread::{self, Thread},
+    time::{Duration, Instant},
+};
+
+/// Scheduler which polls tasks on the thread blocked on the runtime. The same
+/// thread drives the reactor, when there are no tasks to poll.
+pub(crate) struct CurrentThread {
+    /// Scheduled tasks of the priority classes. Tasks can be woken from other
+    /// threads.
+    queues: [Injector<RawTask>; 3],
+    /// Max number of polls of higher classes while a class has runnable tasks
+    aging: NonZeroU32,
+    /// The driving thread waits for reactor events
+    parked: AtomicBool,
+    /// The driving thread must check its condition before waiting for events
+    notified: AtomicBool,
+    shutdown: AtomicBool,
+    /// Number of polled tasks
+    polls: AtomicU64,
+    /// Held by the thread which drives the runtime
+    driver: Mutex<()>,
+    /// Threads blocked on the runtime which wait for the turn to drive it
+    waiters: Mutex<Vec<Thread>>,
+}
+
+impl CurrentThread {
+    pub(crate) fn new(aging: NonZeroU32) -> Self {
+        Self {
+            queues: [Injector::new(), Injector::new(), Injector::new()],
+            aging,
+            parked: AtomicBool::new(false),
+            notified: AtomicBool::new(false),
+            shutdown: AtomicBool::new(false),
+            polls: AtomicU64::new(0),
+            driver: Mutex::new(()),
+            waiters: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Add the scheduled task to the queue and unpark the driving thread
+    pub(crate) fn push(&self, task: RawTask, reactor: &Reactor) {
+        if self.is_shutdown() {
+            // Nobody will poll the task
+            return;
+        }
+
+        self.queues[task.header().priority.index()].push(task);
+
+        // Pairs with the fence in `CurrentThread::park`: either the driver sees
+        // the task or we see the driver
+        atomic::fence(Ordering::SeqCst);
+
+        if self.parked.load(Ordering::SeqCst) {
+            reactor.unpark();
+        }
+    }
+
+    /// Make the blocked thread check its condition. The thread either drives
+    /// the runtime or is parked waiting for the turn.
+    pub(crate) fn unpark(&self, thread: &Thread, reactor: &Reactor) {
+        thread.unpark();
+        self.notified.store(true, Ordering::SeqCst);
+
+        if self.parked.load(Ordering::SeqCst) {
+            reactor.unpark();
+        }
+    }
+
+    /// Poll the tasks and the reactor on the current thread until `f` returns
+    /// `Some`. Only one thread drives the runtime at a time, the others only
+    /// check `f` until the driver is released. Returns `None` if the deadline
+    /// is reached or the scheduler is shut down before, because nothing can
+    /// wake `f` anymore.
+    pub(crate) fn run_until<T>(
+        &self,
+        reactor: &Reactor,
+        threads: &ThreadConfig,
+        deadline: Option<Instant>,
+        mut f: impl FnMut() -> Option<T>,
+    ) -> Option<T> {
+        loop {
+            if let Some(out) = f() {
+                return Some(out);
+            }
+
+            if self.is_shutdown() || is_elapsed(deadline) {
+                return None;
+            }
+
+            if let Some(driver) = self.driver.try_lock() {
+                let out = self.drive(reactor, threads, deadline, &mut f);
+                drop(driver);
+
+                // Pass the turn to the waiting threads
+                for thread in self.waiters.lock().drain(..) {
+                    thread.unpark();
+                }
+
+                return out;
+            }
+
+            {
+                let current = thread::current();
+                let mut waiters = self.waiters.lock();
+
+                // The thread stays in the list until the driver is released
+                if waiters.iter().all(|t| t.id() != current.id()) {
+                    waiters.push(current);
+                }
+            }
+
+            // The driver may have been released before we were added
+            if !self.driver.is_locked() {
+                continue;
+            }
+
+            match timeout(deadline) {
+                Some(timeout) => thread::park_timeout(timeout),
+                None => thread::park(),
+            }
+        }
+    }
+
+    fn drive<T>(
+        &self,
+        reactor: &Reactor,
+        threads: &ThreadConfig,
+        deadline: Option<Instant>,
+        mut f: impl FnMut() -> Option<T>,
+    ) -> Option<T> {
+        let mut polls = 0;
+        let mut aging = Aging::new(self.aging);
+
+        loop {
+            if let Some(out) = f() {
+                return Some(out);
+            }
+
+            if self.is_shutdown() || is_elapsed(deadline) {
+                return None;
+            }
+
+            match self.next_task(&mut aging) {
+                Some(task) => {
+                    task.run();
+                    polls += 1;
+                    self.polls.fetch_add(1, Ordering::Relaxed);
+
+                    // Don't starve the I/O while tasks keep waking each other
+                    if polls == EVENT_INTERVAL {
+                        polls = 0;
+                        reactor.drive(Some(Duration::ZERO));
+                    }
+                }
+                None => self.park(reactor, threads, timeout(deadline)),
+            }
+        }
+    }
+
+    fn is_shutdown(&self) -> bool {
+        self.shutdown.load(Ordering::SeqCst)
+    }
+
+    /// Fill the counters and gauges of the scheduler
+    pub(crate) fn metrics(&self, metrics: &mut RuntimeMetrics) {
+        metrics.polls = self.polls.load(Ordering::Relaxed);
+        metrics.global_queue_depth = self.queues.iter().map(Injector::len).sum();
+    }
+
+    /// Stop accepting tasks, drop the queued ones and wake the driving thread,
+    /// so it stops polling
+    pub(crate) fn shutdown(&self, reactor: &Reactor) {
+        self.shutdown.store(true, Ordering::SeqCst);
+
+        for queue in &self.queues {
+            while steal(|| queue.steal()).is_some() {}
+        }
+
+        if self.parked.load(Ordering::SeqCst) {
+            reactor.unpark();
+        }
+    }
+
+    /// Take the task of the highest class, unless a lower class is starving
+    fn next_task(&self, aging: &mut Aging) -> Option<RawTask> {
+        let (class, task) = aging.order().into_iter().find_map(|class| {
+            let task = steal(|| self.queues[class.index()].steal())?;
+            Some((class, task))
+        })?;
+
+        aging.polled(class, self.queues.each_ref().map(|q| !q.is_empty()));
+        Some(task)
+    }
+
+    /// Wait for reactor events until the timeout, unless a task is scheduled or
+    /// the driving thread is notified meanwhile
+    fn park(&self, reactor: &Reactor, threads: &ThreadConfig, timeout: Option<Duration>) {
+        self.parked.store(true, Ordering::SeqCst);
+        atomic::fence(Ordering::SeqCst);
+
+        if self.queues.iter().all(Injector::is_empty)
+            && !self.notified.swap(false, Ordering::SeqCst)
+            && !self.is_shutdown()
+        {
+            threads.park(|| reactor.drive(timeout));
+        }
+
+        self.parked.store(false, Ordering::SeqCst);
+    }
+}
+
+fn is_elapsed(deadline: Option<Instant>) -> bool {
+    deadline.is_some_and(|deadline| Instant::now() >= deadline)
+}
+
+/// Time left until the deadline, if any
+fn timeout(deadline: Option<Instant>) -> Option<Duration> {
+    deadline.map(|deadline| deadline.saturating_duration_since(Instant::now()))
+}
@@ -1,22 +1,117 @@
+use super::task::RawTask;
+use crate::task::Id;
 use futures::{channel::oneshot, FutureExt};
 use std::{
+    any::Any,
+    fmt,
     future::Future,
+    io,
     pin::Pin,
     task::{Context, Poll},
 };
 
 #[derive(Debug, thiserror::Error)]
-#[error("join fail: result channel dropped")]
-pub struct JoinError;
+pub enum JoinError {
+    #[error("join fail: task was cancelled")]
+    Cancelled,
+    #[error("join fail: runtime is shut down")]
+    Shutdown,
+    #[error("join fail: blocking queue is full")]
+    QueueFull,
+    #[error("join fail: failed to spawn a thread for the job: {0}")]
+    Spawn(io::Error),
+    #[error("join fail: task panicked: {}", panic_message(&**.0))]
+    Panic(Box<dyn Any + Send + 'static>),
+    #[error("join fail: result channel dropped")]
+    ChannelDropped,
+}
+
+impl JoinError {
+    /// Check if the task was cancelled
+    pub fn is_cancelled(&self) -> bool {
+        matches!(self, Self::Cancelled)
+    }
+
+    /// Check if the task was cancelled by the runtime shutdown
+    pub fn is_shutdown(&self) -> bool {
+        matches!(self, Self::Shutdown)
+    }
+
+    /// Check if the blocking job was rejected because the queue of the
+    /// blocking pool was full
+    pub fn is_queue_full(&self) -> bool {
+        matches!(self, Self::QueueFull)
+    }
+
+    /// Check if the blocking job was rejected because the pool had no thread
+    /// and failed to spawn one
+    pub fn is_spawn(&self) -> bool {
+        matches!(self, Self::Spawn(_))
+    }
+
+    /// Check if the task panicked
+    pub fn is_panic(&self) -> bool {
+        matches!(self, Self::Panic(_))
+    }
+
+    /// Consume the error, returning the panic payload if the task panicked.
+    /// The payload can be passed to `std::panic::resume_unwind`.
+    pub fn try_into_panic(self) -> Result<Box<dyn Any + Send + 'static>, Self> {
+        match self {
+            Self::Panic(payload) => Ok(payload),
+            err => Err(err),
+        }
+    }
+}
+
+/// Extract the message of a panic payload, if it is a string
+fn panic_message(payload: &(dyn Any + Send)) -> &str {
+    payload
+        .downcast_ref::<&str>()
+        .copied()
+        .or_else(|| payload.downcast_ref::<String>().map(String::as_str))
+        .unwrap_or("Box<dyn Any>")
+}
+
+pub struct JoinHandle<T> {
+    rx: oneshot::Receiver<Result<T, JoinError>>,
+    raw: AbortHandle,
+}
+
+impl<T> JoinHandle<T> {
+    pub(crate) fn new(rx: oneshot::Receiver<Result<T, JoinError>>, raw: RawTask) -> Self {
+        Self {
+            rx,
+            raw: AbortHandle(raw),
+        }
+    }
+
+    /// Abort the task. Its future will be dropped the next time it would be
+    /// polled and awaiting this handle will return `JoinError::Cancelled`.
+    /// A blocking job is dropped only if it hasn't started yet.
+    pub fn abort(&self) {
+        self.raw.abort();
+    }
+
+    /// Create a handle that can abort the task without awaiting it
+    pub fn abort_handle(&self) -> AbortHandle {
+        self.raw.clone()
+    }
 
-pub struct JoinHandle<T>(oneshot::Receiver<T>);
+    /// Check if the task has finished (completed or cancelled)
+    pub fn is_finished(&self) -> bool {
+        self.raw.is_finished()
+    }
 
-impl<T> JoinHandle<T>
-where
-    T: Send + 'static,
-{
-    pub(crate) fn new(rx: oneshot::Receiver<T>) -> Self {
-        Self(rx)
+    /// Identifier of the task
+    pub fn id(&self) -> Id {
+        self.raw.id()
+    }
+}
+
+impl<T> fmt::Debug for JoinHandle<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.raw.fmt_task(f.debug_struct("JoinHandle"))
     }
 }
 
@@ -24,6 +119,44 @@ impl<T> Future for JoinHandle<T> {
     type Output = Result<T, JoinError>;
 
     fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
-        self.0.poll_unpin(cx).map_err(|_| JoinError)
+        self.rx
+            .poll_unpin(cx)
+            .map(|res| res.unwrap_or(Err(JoinError::ChannelDropped)))
+    }
+}
+
+/// Owned permission to abort a task
+#[derive(Clone)]
+pub struct AbortHandle(RawTask);
+
+impl AbortHandle {
+    /// Abort the task. Has no effect if the task has already finished.
+    pub fn abort(&self) {
+        self.0.abort();
+    }
+
+    /// Check if the task has finished (completed or cancelled)
+    pub fn is_finished(&self) -> bool {
+        self.0.is_finished()
+    }
+
+    /// Identifier of the task
+    pub fn id(&self) -> Id {
+        self.0.header().id
+    }
+
+    fn fmt_task(&self, mut f: fmt::DebugStruct<'_, '_>) -> fmt::Result {
+        let header = self.0.header();
+
+        f.field("id", &header.id)
+            .field("name", &header.name)
+            .field("location", &format_args!("{}", header.location))
+            .finish()
+    }
+}
+
+impl fmt::Debug for AbortHandle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.fmt_task(f.debug_struct("AbortHandle"))
     }
 }
@@ -0,0 +1,233 @@
+use super::{
+    owned::OwnedTasks,
+    task::{RawTask, Schedule},
+};
+use crate::{
+    task::{task_local::Inherit, Id, Priority},
+    BlockOnError, JoinHandle, Runtime,
+};
+use futures::task::AtomicWaker;
+use parking_lot::Mutex;
+use std::{
+    cell::RefCell,
+    collections::VecDeque,
+    future::Future,
+    marker::PhantomData,
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+};
+
+/// Number of local tasks polled before yielding to the runtime
+const BUDGET: usize = 61;
+
+thread_local! {
+    /// Local set which is driven by the current thread
+    static CURRENT: RefCell<Option<Arc<Shared>>> = const { RefCell::new(None) };
+}
+
+/// Set of tasks which are not `Send`. The tasks are polled only on the thread
+/// which drives the set with [`LocalSet::run_until`] or [`LocalSet::block_on`],
+/// so they can hold `Rc` or `RefCell` across awaits, while still using the I/O
+/// types of the runtime.
+///
+/// Dropping the set drops the futures of its unfinished tasks.
+pub struct LocalSet {
+    shared: Arc<Shared>,
+    /// Futures of the tasks must be dropped on the thread where they were created
+    _not_send: PhantomData<*const ()>,
+}
+
+struct Shared {
+    /// Scheduled tasks. Tasks can be woken from other threads.
+    queue: Mutex<VecDeque<RawTask>>,
+    /// Waker of the future which drives the set
+    waker: AtomicWaker,
+    /// Alive tasks, cancelled when the set is dropped
+    owned: OwnedTasks,
+}
+
+/// Task spawned on a local set
+struct Local(Arc<Shared>);
+
+/// Future which polls the local tasks until the inner future is completed
+pub struct RunUntil<'a, F> {
+    local: &'a LocalSet,
+    fut: Pin<Box<F>>,
+}
+
+impl LocalSet {
+    pub fn new() -> Self {
+        Self {
+            shared: Arc::new(Shared {
+                queue: Mutex::new(VecDeque::new()),
+                waker: AtomicWaker::new(),
+                owned: OwnedTasks::new(),
+            }),
+            _not_send: PhantomData,
+        }
+    }
+
+    /// Spawn the task on this set. The task is polled only while the set is
+    /// driven.
+    #[track_caller]
+    pub fn spawn_local<F>(&self, fut: F) -> JoinHandle<F::Output>
+    where
+        F: Future + 'static,
+        F::Output: 'static,
+    {
+        self.shared.spawn(fut, None)
+    }
+
+    /// Drive the set while polling the future. The future must be awaited on
+    /// the runtime, e.g. with [`Runtime::block_on`].
+    pub fn run_until<F>(&self, fut: F) -> RunUntil<'_, F>
+    where
+        F: Future,
+    {
+        RunUntil {
+            local: self,
+            fut: Box::pin(fut),
+        }
+    }
+
+    /// Block current thread on the future, driving the set on it
+    pub fn block_on<F>(&self, rt: &Runtime, fut: F) -> Result<F::Output, BlockOnError>
+    where
+        F: Future,
+    {
+        rt.block_on(self.run_until(fut))
+    }
+
+    /// Make the set current for the thread until the guard is dropped
+    fn enter(&self) -> EnterGuard {
+        let prev = CURRENT.with_borrow_mut(|current| current.replace(Arc::clone(&self.shared)));
+        EnterGuard { prev }
+    }
+}
+
+impl Default for LocalSet {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Drop for LocalSet {
+    fn drop(&mut self) {
+        let _enter = self.enter();
+
+        // Futures are dropped on this thread, before other threads may drop
+        // the last task references
+        self.shared.owned.close();
+        self.shared.owned.shutdown_all();
+        self.shared.queue.lock().clear();
+    }
+}
+
+impl<F> Future for RunUntil<'_, F>
+where
+    F: Future,
+{
+    type Output = F::Output;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let _enter = self.local.enter();
+        let shared = &self.local.shared;
+
+        shared.waker.register(cx.waker());
+
+        if let Poll::Ready(out) = self.fut.as_mut().poll(cx) {
+            return Poll::Ready(out);
+        }
+
+        let shared = Arc::clone(shared);
+
+        for _ in 0..BUDGET {
+            // The lock must be released before the task is polled
+            let task = shared.queue.lock().pop_front();
+
+            match task {
+                Some(task) => task.run(),
+                None => return Poll::Pending,
+            }
+        }
+
+        // There are more tasks, so the set must be polled again after the
+        // other tasks of the runtime
+        cx.waker().wake_by_ref();
+        Poll::Pending
+    }
+}
+
+impl Shared {
+    #[track_caller]
+    fn spawn<F>(self: &Arc<Self>, fut: F, name: Option<String>) -> JoinHandle<F::Output>
+    where
+        F: Future + 'static,
+        F::Output: 'static,
+    {
+        let (task, jh) = RawTask::new(
+            Inherit::new(fut),
+            Local(Arc::clone(self)),
+            name,
+            Priority::Normal,
+        );
+
+        if self.owned.bind(&task) {
+            task.wake();
+        } else {
+            task.shutdown();
+        }
+
+        jh
+    }
+}
+
+impl Schedule for Local {
+    fn schedule(&self, task: RawTask) {
+        self.0.queue.lock().push_back(task);
+        self.0.waker.wake();
+    }
+
+    fn release(&self, id: Id) {
+        self.0.owned.remove(id);
+    }
+}
+
+/// Restores the previous local set of the thread on drop
+struct EnterGuard {
+    prev: Option<Arc<Shared>>,
+}
+
+impl Drop for EnterGuard {
+    fn drop(&mut self) {
+        let prev = self.prev.take();
+        CURRENT.with_borrow_mut(|current| *current = prev);
+    }
+}
+
+/// Spawn the task on the local set which is driven by the current thread
+///
+/// # Panics
+///
+/// Panics if called outside of a local set
+#[track_caller]
+pub fn spawn_local<F>(fut: F) -> JoinHandle<F::Output>
+where
+    F: Future + 'static,
+    F::Output: 'static,
+{
+    spawn_named(fut, None)
+}
+
+#[track_caller]
+pub(crate) fn spawn_named<F>(fut: F, name: Option<String>) -> JoinHandle<F::Output>
+where
+    F: Future + 'static,
+    F::Output: 'static,
+{
+    CURRENT
+        .with_borrow(|current| current.clone())
+        .expect("spawn_local must be called from the context of a local set")
+        .spawn(fut, name)
+}
@@ -1,113 +1,389 @@
+pub(crate) mod blocking;
 pub(crate) mod handle;
+pub(crate) mod local;
 
+mod aging;
+mod current_thread;
+mod owned;
+pub(crate) mod pool;
+mod root;
+mod state;
 mod task;
+mod threads;
+mod waker;
 
-use self::task::{BlockedOnTaskWaker, SpawnedTaskWaker, Task};
-use crate::JoinHandle;
-use futures::channel::oneshot;
-use parking_lot::Mutex;
+pub(crate) use self::{
+    blocking::{BlockingPool, PoolKind},
+    threads::{ThreadConfig, ThreadKind},
+};
+
+use self::{
+    blocking::{BlockingTask, QueueFullError},
+    current_thread::CurrentThread,
+    owned::OwnedTasks,
+    root::{Root, Unpark},
+    task::{Blocking, RawTask, Spawned},
+};
+use crate::{
+    reactor::Reactor,
+    runtime::context,
+    task::{task_local::Inherit, Priority},
+    JoinError, JoinHandle, RuntimeMetrics,
+};
+use crossbeam_deque::Steal;
+use pool::TaskPool;
 use std::{
     future::Future,
-    pin::Pin,
-    sync::{Arc, OnceLock},
-    task::{Context, Poll, Wake},
-    thread::{self, Thread},
+    io, iter,
+    num::{NonZeroU32, NonZeroUsize},
+    pin::pin,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    thread,
+    time::{Duration, Instant},
 };
-use tpool::ThreadPool;
-use zeet::WorkStealThreadPool;
 
+/// Number of polls between checks of the reactor events and, on a worker, of
+/// the global queue, so neither of them is starved by the tasks which keep
+/// waking each other
+const EVENT_INTERVAL: u32 = 61;
+
+/// Runtime state shared by the runtime, its worker threads and tasks
 pub struct Executor {
-    task_tp: WorkStealThreadPool,
-    blocking_tp: ThreadPool,
-    block_on_thr: Mutex<Option<Thread>>,
+    scheduler: Scheduler,
+    blocking: BlockingPool,
+    /// Pool of the CPU-bound jobs
+    compute: BlockingPool,
+    reactor: Arc<Reactor>,
+    /// Settings of the worker, blocking and compute threads
+    threads: ThreadConfig,
+    /// Alive tasks, cancelled on shutdown
+    owned: OwnedTasks,
+    is_shutdown: AtomicBool,
 }
 
-static EXECUTOR: OnceLock<Executor> = OnceLock::new();
+/// Strategy of polling the scheduled tasks
+pub enum Flavor {
+    /// Tasks are polled by the given number of worker threads
+    MultiThread(NonZeroUsize),
+    /// Tasks are polled by the thread blocked on the runtime
+    CurrentThread,
+}
+
+enum Scheduler {
+    MultiThread(TaskPool),
+    CurrentThread(Box<CurrentThread>),
+}
 
 impl Executor {
-    pub fn new(task_tp: WorkStealThreadPool, blocking_tp: ThreadPool) -> Self {
-        Self {
-            task_tp,
-            blocking_tp,
-            block_on_thr: Mutex::new(None),
+    /// Create the executor and start its worker threads
+    pub fn start(
+        flavor: Flavor,
+        blocking: BlockingPool,
+        compute: BlockingPool,
+        reactor: Reactor,
+        priority_aging: NonZeroU32,
+        threads: ThreadConfig,
+    ) -> io::Result<Arc<Self>> {
+        let reactor = Arc::new(reactor);
+
+        let scheduler = match flavor {
+            Flavor::MultiThread(_) => {
+                Scheduler::MultiThread(TaskPool::new(Arc::clone(&reactor), priority_aging))
+            }
+            Flavor::CurrentThread => {
+                Scheduler::CurrentThread(Box::new(CurrentThread::new(priority_aging)))
+            }
+        };
+
+        let exec = Arc::new(Self {
+            scheduler,
+            blocking,
+            compute,
+            reactor,
+            threads,
+            owned: OwnedTasks::new(),
+            is_shutdown: AtomicBool::new(false),
+        });
+
+        if let (Flavor::MultiThread(threads), Scheduler::MultiThread(pool)) =
+            (flavor, &exec.scheduler)
+        {
+            if let Err(e) = pool.start(threads, &exec) {
+                exec.shutdown(Instant::now(), None);
+                return Err(e);
+            }
         }
-    }
 
-    pub fn get() -> &'static Executor {
-        EXECUTOR.get().expect("executor is not set")
+        Ok(exec)
     }
 
-    pub fn set_global(self) {
-        EXECUTOR.set(self).ok();
+    pub fn reactor(&self) -> &Arc<Reactor> {
+        &self.reactor
     }
 
-    pub fn block_on<T>(
-        &self,
-        fut: impl Future<Output = T> + Send + 'static,
-    ) -> Result<T, BlockOnError>
+    /// Poll the future in place on the current thread until it's completed.
+    /// Spawned tasks are polled by the workers, or by the current thread if
+    /// the executor has no workers.
+    ///
+    /// Fails if the current thread already polls tasks of some runtime, because
+    /// blocking it could deadlock the runtime. It also fails if the runtime is
+    /// shut down before the future completes, as nothing can wake the future
+    /// anymore.
+    pub fn block_on<F>(self: &Arc<Self>, fut: F) -> Result<F::Output, BlockOnError>
     where
-        T: Send + 'static,
+        F: Future,
     {
-        *self.block_on_thr.lock() = Some(thread::current());
+        let Some(_runtime) = context::enter_runtime() else {
+            return Err(BlockOnError::Reentrant);
+        };
 
-        let (task, mut jh) = Task::<T, BlockedOnTaskWaker>::new(fut);
-        task.clone().wake();
+        let _enter = context::enter(Arc::clone(self));
+        let mut fut = pin!(fut);
 
-        let main_waker = Arc::clone(&task).into();
-        let mut cx = Context::from_waker(&main_waker);
+        match &self.scheduler {
+            Scheduler::MultiThread(pool) => {
+                let root = Root::new(Unpark::Thread(thread::current()));
+                let _blocked = pool.block();
 
-        let mut jh = Pin::new(&mut jh);
+                loop {
+                    if let Some(res) = root.poll(fut.as_mut()) {
+                        return res;
+                    }
 
-        loop {
-            // Check if main task is ready
-            if let Poll::Ready(res) = jh.as_mut().poll(&mut cx) {
-                return Ok(res?);
-            }
+                    if pool.is_shutdown() {
+                        return Err(BlockOnError::Shutdown);
+                    }
 
-            // Park this thread until main task become ready
-            thread::park();
+                    // Park this thread until the future is woken or the pool
+                    // is shut down
+                    thread::park();
+                }
+            }
+            Scheduler::CurrentThread(sched) => {
+                let root = Root::new(Unpark::Driver(thread::current(), Arc::clone(self)));
+                sched
+                    .run_until(&self.reactor, &self.threads, None, || {
+                        root.poll(fut.as_mut())
+                    })
+                    .unwrap_or(Err(BlockOnError::Shutdown))
+            }
         }
     }
 
-    pub fn spawn<T>(&self, fut: impl Future<Output = T> + Send + 'static) -> JoinHandle<T>
+    #[track_caller]
+    pub fn spawn<T>(
+        self: &Arc<Self>,
+        fut: impl Future<Output = T> + Send + 'static,
+        name: Option<String>,
+        priority: Priority,
+    ) -> JoinHandle<T>
     where
         T: Send + 'static,
     {
-        let (task, jh) = Task::<T, SpawnedTaskWaker>::new(fut);
+        let (task, jh) = RawTask::new(Inherit::new(fut), Spawned(Arc::clone(self)), name, priority);
+        self.submit(task);
+        jh
+    }
 
-        // Wake the task so that it starts trying to complete
-        task.wake();
+    /// Spawn the synchronous job on the blocking pool. If the queue of the pool
+    /// is full, the job is dropped and the handle returns `JoinError::QueueFull`.
+    /// If the pool has no thread and fails to spawn one, the handle returns
+    /// `JoinError::Spawn`.
+    #[track_caller]
+    pub fn spawn_blocking<T>(
+        self: &Arc<Self>,
+        f: impl FnOnce() -> T + Send + 'static,
+        name: Option<String>,
+    ) -> JoinHandle<T>
+    where
+        T: Send + 'static,
+    {
+        let (task, jh) = RawTask::new(
+            BlockingTask::new(f),
+            Blocking(Arc::clone(self), PoolKind::Blocking),
+            name,
+            Priority::Normal,
+        );
+
+        self.submit(task);
         jh
     }
 
-    pub fn spawn_blocking<T>(&self, f: impl Fn() -> T + Send + 'static) -> JoinHandle<T>
+    /// Spawn the CPU-bound job on the compute pool, so it neither stalls the
+    /// task workers nor waits behind the blocking jobs
+    #[track_caller]
+    pub fn spawn_compute<T>(
+        self: &Arc<Self>,
+        f: impl FnOnce() -> T + Send + 'static,
+        name: Option<String>,
+    ) -> JoinHandle<T>
     where
         T: Send + 'static,
     {
-        let (tx, rx) = oneshot::channel();
-
-        let tx = Mutex::new(Some(tx));
-        self.blocking_tp.spawn(move || {
-            let out = f();
-            if let Some(tx) = tx.lock().take() {
-                tx.send(out).ok();
-            };
-        });
+        let (task, jh) = RawTask::new(
+            BlockingTask::new(f),
+            Blocking(Arc::clone(self), PoolKind::Compute),
+            name,
+            Priority::Normal,
+        );
+
+        self.submit(task);
+        jh
+    }
+
+    /// Spawn the synchronous job on the blocking pool, failing if the queue of
+    /// the pool is full. Other rejections are returned by the handle.
+    #[track_caller]
+    pub fn try_spawn_blocking<T>(
+        self: &Arc<Self>,
+        f: impl FnOnce() -> T + Send + 'static,
+        name: Option<String>,
+    ) -> Result<JoinHandle<T>, QueueFullError>
+    where
+        T: Send + 'static,
+    {
+        let (task, jh) = RawTask::new(
+            BlockingTask::new(f),
+            Blocking(Arc::clone(self), PoolKind::Blocking),
+            name,
+            Priority::Normal,
+        );
+
+        if !self.owned.bind(&task) {
+            task.shutdown();
+            return Ok(jh);
+        }
+
+        // The task is shut down if it's not idle anymore
+        let Some(task) = task.try_schedule() else {
+            return Ok(jh);
+        };
+
+        match self.blocking.push(task, self) {
+            Ok(()) => Ok(jh),
+            Err((task, err)) => {
+                let full = err.is_queue_full();
+                task.reject(err);
 
-        JoinHandle::new(rx)
+                if full {
+                    Err(QueueFullError)
+                } else {
+                    Ok(jh)
+                }
+            }
+        }
     }
 
-    fn unpark_blocked_thread(&self) {
-        self.block_on_thr
-            .lock()
-            .take()
-            .expect("block on thread is not set")
-            .unpark();
+    /// Shut the runtime down. New tasks are cancelled right away, while the
+    /// alive ones are given time to complete until `grace_deadline`. Tasks of
+    /// a current-thread runtime are polled by the calling thread meanwhile,
+    /// unless it already polls tasks of some runtime. Then the
+    /// remaining tasks are cancelled, their futures are dropped and the threads
+    /// are stopped. Threads are waited for during `threads_timeout` after the
+    /// grace period, if any, and are left to finish in background after it.
+    /// Blocking jobs which are already running can't be interrupted.
+    ///
+    /// Only the first call has an effect.
+    pub fn shutdown(self: &Arc<Self>, grace_deadline: Instant, threads_timeout: Option<Duration>) {
+        if self.is_shutdown.swap(true, Ordering::AcqRel) {
+            return;
+        }
+
+        self.owned.close();
+
+        match (&self.scheduler, context::enter_runtime()) {
+            // Nothing polls the tasks of a current-thread runtime, unless some
+            // thread is blocked on it
+            (Scheduler::CurrentThread(sched), Some(_runtime)) => {
+                let _enter = context::enter(Arc::clone(self));
+
+                sched.run_until(&self.reactor, &self.threads, Some(grace_deadline), || {
+                    self.owned.is_empty().then_some(())
+                });
+            }
+            _ => {
+                self.owned.wait_empty(grace_deadline);
+            }
+        }
+
+        // Threads get their own budget, so the ones which are idle are joined
+        // even if the grace period is used up
+        let threads_deadline = threads_timeout.map(|timeout| Instant::now() + timeout);
+
+        match &self.scheduler {
+            Scheduler::MultiThread(pool) => pool.shutdown(threads_deadline),
+            Scheduler::CurrentThread(sched) => sched.shutdown(&self.reactor),
+        }
+
+        self.owned.shutdown_all();
+        self.reactor.shutdown();
+        self.blocking.shutdown(threads_deadline);
+        self.compute.shutdown(threads_deadline);
     }
+
+    /// Snapshot of the runtime counters and gauges
+    pub fn metrics(&self) -> RuntimeMetrics {
+        let mut metrics = RuntimeMetrics {
+            blocking: self.blocking.metrics(),
+            compute: self.compute.metrics(),
+            io_events: self.reactor.events(),
+            io_sources: self.reactor.sources(),
+            ..Default::default()
+        };
+
+        self.owned.metrics(&mut metrics);
+
+        match &self.scheduler {
+            Scheduler::MultiThread(pool) => pool.metrics(&mut metrics),
+            Scheduler::CurrentThread(sched) => sched.metrics(&mut metrics),
+        }
+
+        metrics
+    }
+
+    /// Pool of the synchronous jobs of the kind
+    fn pool(&self, kind: PoolKind) -> &BlockingPool {
+        match kind {
+            PoolKind::Blocking => &self.blocking,
+            PoolKind::Compute => &self.compute,
+        }
+    }
+
+    /// Submit the task for execution
+    fn schedule(&self, task: RawTask) {
+        match &self.scheduler {
+            Scheduler::MultiThread(pool) => pool.push(task),
+            Scheduler::CurrentThread(sched) => sched.push(task, &self.reactor),
+        }
+    }
+
+    /// Register the new task and wake it, so that it starts trying to complete.
+    /// After shutdown the task is cancelled instead.
+    fn submit(&self, task: RawTask) {
+        if self.owned.bind(&task) {
+            task.wake();
+        } else {
+            task.shutdown();
+        }
+    }
+}
+
+/// Retry the steal from a task queue until it's finished
+fn steal(mut f: impl FnMut() -> Steal<RawTask>) -> Option<RawTask> {
+    iter::repeat_with(&mut f)
+        .find(|s| !s.is_retry())
+        .and_then(Steal::success)
 }
 
 #[derive(Debug, thiserror::Error)]
 pub enum BlockOnError {
     #[error("join error: {0}")]
     Join(#[from] handle::JoinError),
+    #[error("block_on is called from a thread which polls runtime tasks")]
+    Reentrant,
+    #[error("runtime is shut down")]
+    Shutdown,
 }
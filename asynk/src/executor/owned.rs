@@ -0,0 +1,144 @@
+use super::task::RawTask;
+use crate::{task::Id, RuntimeMetrics};
+use parking_lot::{Condvar, Mutex};
+use std::{
+    array,
+    collections::HashMap,
+    mem,
+    sync::atomic::{AtomicU64, AtomicUsize, Ordering},
+    time::Instant,
+};
+
+/// Number of independently locked parts of the task list. Task ids are
+/// sequential, so the tasks spawned in a row go to different shards.
+const SHARDS: usize = 32;
+
+/// Tasks which are alive in the runtime. On shutdown the list is closed for new
+/// tasks and the remaining ones are cancelled.
+///
+/// The list is split into shards by the task id, so spawns and completions on
+/// different threads rarely contend for the same lock.
+pub(crate) struct OwnedTasks {
+    shards: [Mutex<Shard>; SHARDS],
+    /// Number of tasks in the list
+    alive: AtomicUsize,
+    /// Number of added tasks
+    spawned: AtomicU64,
+    /// Number of removed tasks, including the cancelled ones
+    completed: AtomicU64,
+    /// Held to wait for the list to become empty
+    empty_lock: Mutex<()>,
+    /// Notified when the last task is removed
+    empty: Condvar,
+}
+
+#[derive(Default)]
+struct Shard {
+    tasks: HashMap<Id, RawTask>,
+    /// Set in every shard on close, so no task is added after it
+    closed: bool,
+}
+
+impl OwnedTasks {
+    pub(crate) fn new() -> Self {
+        Self {
+            shards: array::from_fn(|_| Mutex::default()),
+            alive: AtomicUsize::new(0),
+            spawned: AtomicU64::new(0),
+            completed: AtomicU64::new(0),
+            empty_lock: Mutex::new(()),
+            empty: Condvar::new(),
+        }
+    }
+
+    /// Add the task to the list. Returns `false` if the list is closed, so the
+    /// task must be shut down by the caller.
+    pub(crate) fn bind(&self, task: &RawTask) -> bool {
+        let id = task.header().id;
+        let mut shard = self.shard(id).lock();
+
+        if shard.closed {
+            return false;
+        }
+
+        shard.tasks.insert(id, task.clone());
+        self.alive.fetch_add(1, Ordering::Relaxed);
+        self.spawned.fetch_add(1, Ordering::Relaxed);
+        true
+    }
+
+    /// Remove the completed task
+    pub(crate) fn remove(&self, id: Id) {
+        let task = self.shard(id).lock().tasks.remove(&id);
+
+        if task.is_some() {
+            self.removed(1);
+        }
+
+        // The last reference may be dropped, so it's done outside the lock
+        drop(task);
+    }
+
+    /// Fill the task counters
+    pub(crate) fn metrics(&self, metrics: &mut RuntimeMetrics) {
+        metrics.alive_tasks = self.alive.load(Ordering::Relaxed);
+        metrics.spawned_tasks = self.spawned.load(Ordering::Relaxed);
+        metrics.completed_tasks = self.completed.load(Ordering::Relaxed);
+    }
+
+    /// Stop accepting new tasks
+    pub(crate) fn close(&self) {
+        for shard in &self.shards {
+            shard.lock().closed = true;
+        }
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.alive.load(Ordering::Acquire) == 0
+    }
+
+    /// Wait until all tasks are completed or the deadline is reached. Returns
+    /// `true` if there are no tasks left.
+    pub(crate) fn wait_empty(&self, deadline: Instant) -> bool {
+        let mut lock = self.empty_lock.lock();
+
+        while self.alive.load(Ordering::Acquire) > 0 {
+            if self.empty.wait_until(&mut lock, deadline).timed_out() {
+                return self.alive.load(Ordering::Acquire) == 0;
+            }
+        }
+
+        true
+    }
+
+    /// Cancel all remaining tasks
+    pub(crate) fn shutdown_all(&self) {
+        for shard in &self.shards {
+            let tasks = mem::take(&mut shard.lock().tasks);
+            self.removed(tasks.len());
+
+            for task in tasks.into_values() {
+                task.shutdown();
+            }
+        }
+    }
+
+    fn shard(&self, id: Id) -> &Mutex<Shard> {
+        &self.shards[id.as_u64() as usize % SHARDS]
+    }
+
+    /// Count the tasks out of the list, notifying the waiters if it's empty
+    fn removed(&self, count: usize) {
+        if count == 0 {
+            return;
+        }
+
+        self.completed.fetch_add(count as u64, Ordering::Relaxed);
+
+        if self.alive.fetch_sub(count, Ordering::AcqRel) == count {
+            // Pairs with the check in `wait_empty` under the lock
+            let _lock = self.empty_lock.lock();
+            self.empty.notify_all();
+        }
+    }
+}
@@ -0,0 +1,598 @@
+use super::{
+    aging::Aging,
+    steal,
+    task::{Header, RawTask},
+    threads::{self, ThreadKind},
+    Executor, EVENT_INTERVAL,
+};
+use crate::{reactor::Reactor, runtime::context, task::Priority, RuntimeMetrics};
+use crossbeam_deque::{Injector, Stealer, Worker as LocalQueue};
+use parking_lot::{Condvar, Mutex};
+use slab::Slab;
+use std::{
+    cell::Cell,
+    io, mem,
+    num::{NonZeroU32, NonZeroUsize},
+    ptr,
+    sync::{
+        atomic::{self, AtomicBool, AtomicU64, AtomicUsize, Ordering},
+        Arc, OnceLock,
+    },
+    thread::{self, JoinHandle, Thread},
+    time::{Duration, Instant},
+};
+
+/// Max number of consecutive polls from the LIFO slot, so the tasks which keep
+/// waking each other don't starve the local queue
+const MAX_LIFO_POLLS: u32 = 3;
+
+thread_local! {
+    /// Worker of the current thread, if the thread belongs to a task pool
+    static WORKER: Cell<*const Worker> = const { Cell::new(ptr::null()) };
+}
+
+/// Work-stealing thread pool polling scheduled tasks. Tasks are submitted as
+/// task references, so scheduling doesn't allocate.
+///
+/// Every worker has a local queue, which other workers steal from when they are
+/// out of tasks, and a LIFO slot for the task woken by the task being polled.
+/// Tasks scheduled from other threads go to the global queue. Idle workers poll
+/// the reactor events.
+///
+/// Tasks of the high and background priorities always go to the global queues
+/// of their classes, the local queues hold the normal tasks only.
+pub(crate) struct TaskPool {
+    shared: Arc<Shared>,
+}
+
+struct Shared {
+    /// Global queues of the priority classes. Normal tasks go there when they
+    /// are scheduled from outside of the workers.
+    injectors: [Injector<RawTask>; 3],
+    /// Max number of polls of higher classes while a class has runnable tasks
+    aging: NonZeroU32,
+    /// Parts of the workers accessed by the other threads
+    remotes: OnceLock<Vec<Remote>>,
+    reactor: Arc<Reactor>,
+    /// Number of workers looking for tasks to sleep
+    sleepers: AtomicUsize,
+    /// A sleeping worker waits for the reactor events
+    driver_parked: AtomicBool,
+    shutdown: AtomicBool,
+    /// Number of workers waiting on the condition variable
+    waiters: Mutex<usize>,
+    cvar: Condvar,
+    threads: Mutex<Vec<JoinHandle<()>>>,
+    /// Number of running worker threads
+    alive: Mutex<usize>,
+    /// Notified when a worker thread exits
+    exited: Condvar,
+    /// Threads blocked on the runtime, unparked on shutdown
+    blocked: Mutex<Slab<Thread>>,
+}
+
+/// Registration of a thread blocked on the runtime, removed on drop
+pub(crate) struct Blocked<'a> {
+    shared: &'a Shared,
+    key: usize,
+}
+
+/// State of a worker thread
+struct Worker {
+    shared: Arc<Shared>,
+    exec: Arc<Executor>,
+    /// Queues of the worker. The core is handed over to another thread when
+    /// this one blocks in place, and is taken out while it's used, so tasks
+    /// woken meanwhile go to the global queue.
+    core: Cell<Option<Box<Core>>>,
+    /// Task being polled
+    running: Cell<*const Header>,
+}
+
+/// Counts the worker thread out of the alive ones on drop
+struct ExitGuard(Arc<Shared>);
+
+/// Part of a worker accessed by the other threads
+struct Remote {
+    /// Stealer of the local queue
+    stealer: Stealer<RawTask>,
+    /// Number of polled tasks
+    polls: AtomicU64,
+    /// Number of successful steals from the other workers
+    steals: AtomicU64,
+}
+
+/// Queues of a worker, which may move between threads
+struct Core {
+    index: usize,
+    local: LocalQueue<RawTask>,
+    /// Task woken by the task being polled. It's polled next, because it likely
+    /// waits for data produced by the waker.
+    lifo: Option<RawTask>,
+    polls: u32,
+    lifo_polls: u32,
+    aging: Aging,
+}
+
+impl TaskPool {
+    pub(crate) fn new(reactor: Arc<Reactor>, aging: NonZeroU32) -> Self {
+        let shared = Arc::new(Shared {
+            injectors: [Injector::new(), Injector::new(), Injector::new()],
+            aging,
+            remotes: OnceLock::new(),
+            reactor,
+            sleepers: AtomicUsize::new(0),
+            driver_parked: AtomicBool::new(false),
+            shutdown: AtomicBool::new(false),
+            waiters: Mutex::new(0),
+            cvar: Condvar::new(),
+            threads: Mutex::new(Vec::new()),
+            alive: Mutex::new(0),
+            exited: Condvar::new(),
+            blocked: Mutex::new(Slab::new()),
+        });
+
+        Self { shared }
+    }
+
+    /// Spawn worker threads running in the context of the executor
+    pub(crate) fn start(&self, threads: NonZeroUsize, exec: &Arc<Executor>) -> io::Result<()> {
+        let locals = (0..threads.get())
+            .map(|_| LocalQueue::new_fifo())
+            .collect::<Vec<_>>();
+
+        let remotes = locals
+            .iter()
+            .map(|local| Remote {
+                stealer: local.stealer(),
+                polls: AtomicU64::new(0),
+                steals: AtomicU64::new(0),
+            })
+            .collect();
+
+        if self.shared.remotes.set(remotes).is_err() {
+            panic!("task pool is already started");
+        }
+
+        for (index, local) in locals.into_iter().enumerate() {
+            let core = Box::new(Core {
+                index,
+                local,
+                lifo: None,
+                polls: 0,
+                lifo_polls: 0,
+                aging: Aging::new(self.shared.aging),
+            });
+
+            spawn_worker(&self.shared, exec, core).map_err(|(e, _)| e)?;
+        }
+
+        Ok(())
+    }
+
+    /// Submit the scheduled task. Normal tasks woken on a worker of this pool
+    /// go to its local queue, others go to the global queues.
+    pub(crate) fn push(&self, task: RawTask) {
+        if self.shared.is_shutdown() {
+            // Nobody will poll the task
+            return;
+        }
+
+        // SAFETY: the pointer is set only while the worker is alive on this thread
+        match unsafe { WORKER.get().as_ref() } {
+            Some(worker)
+                if Arc::ptr_eq(&worker.shared, &self.shared)
+                    && task.header().priority == Priority::Normal =>
+            {
+                worker.schedule(task)
+            }
+            _ => self.shared.inject(task),
+        }
+    }
+
+    /// Register the current thread as blocked on the runtime, so it's unparked
+    /// when the pool is shut down
+    pub(crate) fn block(&self) -> Blocked<'_> {
+        let key = self.shared.blocked.lock().insert(thread::current());
+
+        Blocked {
+            shared: &self.shared,
+            key,
+        }
+    }
+
+    pub(crate) fn is_shutdown(&self) -> bool {
+        self.shared.is_shutdown()
+    }
+
+    /// Fill the counters and gauges of the workers
+    pub(crate) fn metrics(&self, metrics: &mut RuntimeMetrics) {
+        let remotes = self.shared.remotes();
+
+        metrics.workers = remotes.len();
+        metrics.polls = remotes
+            .iter()
+            .map(|r| r.polls.load(Ordering::Relaxed))
+            .sum();
+        metrics.steals = remotes
+            .iter()
+            .map(|r| r.steals.load(Ordering::Relaxed))
+            .sum();
+        metrics.worker_queue_depths = remotes.iter().map(|r| r.stealer.len()).collect();
+        metrics.global_queue_depth = self.shared.injectors.iter().map(Injector::len).sum();
+    }
+
+    /// Stop the workers and drop the queued tasks. Waits for the worker threads
+    /// to finish their current polls until the deadline, if any. The current
+    /// thread isn't waited for, if it's one of the workers.
+    pub(crate) fn shutdown(&self, deadline: Option<Instant>) {
+        {
+            let _lock = self.shared.waiters.lock();
+            self.shared.shutdown.store(true, Ordering::Release);
+            self.shared.cvar.notify_all();
+        }
+
+        // Wake the worker which waits for the reactor events
+        self.shared.reactor.unpark();
+
+        // Nothing can wake the blocked futures anymore
+        for (_, thread) in self.shared.blocked.lock().iter() {
+            thread.unpark();
+        }
+
+        let handles = mem::take(&mut *self.shared.threads.lock());
+        threads::join_all(
+            handles,
+            &self.shared.alive,
+            &self.shared.exited,
+            |alive| *alive,
+            deadline,
+        );
+
+        for injector in &self.shared.injectors {
+            while steal(|| injector.steal()).is_some() {}
+        }
+    }
+}
+
+/// Spawn a worker thread owning the core. The core is returned back if the
+/// thread can't be spawned.
+fn spawn_worker(
+    shared: &Arc<Shared>,
+    exec: &Arc<Executor>,
+    core: Box<Core>,
+) -> Result<(), (io::Error, Box<Core>)> {
+    // The core is passed through the slot, so it isn't lost if the spawn fails
+    let slot = Arc::new(Mutex::new(Some(core)));
+
+    *shared.alive.lock() += 1;
+
+    let thread = exec.threads.spawn(ThreadKind::Worker, {
+        let shared = Arc::clone(shared);
+        let exec = Arc::clone(exec);
+        let slot = Arc::clone(&slot);
+
+        move || {
+            // The thread is counted out even if it panics, so the shutdown
+            // doesn't wait for it forever
+            let _exit = ExitGuard(Arc::clone(&shared));
+
+            let _enter = context::enter(Arc::clone(&exec));
+            let _runtime = context::enter_runtime();
+
+            let worker = Worker {
+                shared,
+                exec,
+                core: Cell::new(slot.lock().take()),
+                running: Cell::new(ptr::null()),
+            };
+
+            worker.run();
+        }
+    });
+
+    match thread {
+        Ok(thread) => {
+            shared.threads.lock().push(thread);
+            Ok(())
+        }
+        Err(e) => {
+            *shared.alive.lock() -= 1;
+            let core = slot.lock().take().expect("core is not taken");
+            Err((e, core))
+        }
+    }
+}
+
+/// Run the blocking function on the current thread. If the thread is a worker
+/// of a task pool, its queues are handed over to a new worker thread first, so
+/// the other tasks keep being polled, and this thread exits once the current
+/// task poll is finished.
+pub(crate) fn block_in_place<R>(f: impl FnOnce() -> R) -> R {
+    // SAFETY: the pointer is set only while the worker is alive on this thread
+    let Some(worker) = (unsafe { WORKER.get().as_ref() }) else {
+        return f();
+    };
+
+    // The core is already handed over, if the thread blocks in place again
+    if let Some(core) = worker.core.take() {
+        if let Err((_, core)) = spawn_worker(&worker.shared, &worker.exec, core) {
+            // Without a replacement the tasks of the core wait for `f`
+            worker.core.set(Some(core));
+            return f();
+        }
+    }
+
+    // The thread doesn't poll runtime tasks anymore, so it may block on them
+    context::exit_runtime(f)
+}
+
+impl Shared {
+    fn is_shutdown(&self) -> bool {
+        self.shutdown.load(Ordering::Acquire)
+    }
+
+    fn remotes(&self) -> &[Remote] {
+        self.remotes.get().map_or(&[], Vec::as_slice)
+    }
+
+    /// Check if there are tasks which a sleeping worker could take
+    fn has_tasks(&self) -> bool {
+        self.injectors.iter().any(|i| !i.is_empty())
+            || self.remotes().iter().any(|r| !r.stealer.is_empty())
+    }
+
+    fn injector(&self, class: Priority) -> &Injector<RawTask> {
+        &self.injectors[class.index()]
+    }
+
+    /// Submit the task to the global queue of its class
+    fn inject(&self, task: RawTask) {
+        self.injector(task.header().priority).push(task);
+        self.notify();
+    }
+
+    /// Wake a sleeping worker to take the new task
+    fn notify(&self) {
+        // Pairs with the fence in `Worker::park`: either the worker sees the
+        // task or we see the worker
+        atomic::fence(Ordering::SeqCst);
+
+        if self.sleepers.load(Ordering::SeqCst) == 0 {
+            return;
+        }
+
+        let waiters = self.waiters.lock();
+
+        if *waiters > 0 {
+            self.cvar.notify_one();
+        } else if self.driver_parked.load(Ordering::SeqCst) {
+            self.reactor.unpark();
+        }
+    }
+
+    fn exit(&self) {
+        *self.alive.lock() -= 1;
+        self.exited.notify_all();
+    }
+}
+
+impl Drop for Blocked<'_> {
+    fn drop(&mut self) {
+        self.shared.blocked.lock().remove(self.key);
+    }
+}
+
+impl Drop for ExitGuard {
+    fn drop(&mut self) {
+        self.0.exit();
+    }
+}
+
+impl Worker {
+    fn run(&self) {
+        WORKER.set(self);
+
+        while !self.shared.is_shutdown() {
+            // The core is handed over to another thread
+            let Some(task) = self.next_task() else {
+                break;
+            };
+
+            match task {
+                Some(task) => self.poll(task),
+                None => self.park(),
+            }
+        }
+
+        WORKER.set(ptr::null());
+
+        match self.core.take() {
+            // Drop the references of the queued tasks
+            Some(core) => drop(core),
+            // The thread blocked in place and isn't needed anymore
+            None => {
+                let current = thread::current().id();
+
+                self.shared
+                    .threads
+                    .lock()
+                    .retain(|t| t.thread().id() != current);
+            }
+        }
+    }
+
+    fn poll(&self, task: RawTask) {
+        self.running.set(task.header());
+        task.run();
+        self.running.set(ptr::null());
+
+        self.with_core(|core| {
+            core.polls += 1;
+            self.shared.remotes()[core.index]
+                .polls
+                .fetch_add(1, Ordering::Relaxed);
+        });
+    }
+
+    /// Call `f` with the core taken out of the worker. Returns `None` if the
+    /// worker has no core.
+    fn with_core<R>(&self, f: impl FnOnce(&mut Core) -> R) -> Option<R> {
+        let mut core = self.core.take()?;
+        let res = f(&mut core);
+        self.core.set(Some(core));
+        Some(res)
+    }
+
+    /// Submit the task woken on this worker
+    fn schedule(&self, task: RawTask) {
+        let Some(mut core) = self.core.take() else {
+            self.shared.inject(task);
+            return;
+        };
+
+        // The task woken by itself yields, so it goes behind the others
+        if ptr::eq(task.header(), self.running.get()) {
+            core.local.push(task);
+            self.shared.notify();
+        } else if let Some(prev) = core.lifo.replace(task) {
+            core.local.push(prev);
+            self.shared.notify();
+        }
+
+        self.core.set(Some(core));
+    }
+
+    /// Find the next task to poll. Returns `None` if the worker has no core.
+    fn next_task(&self) -> Option<Option<RawTask>> {
+        let maintenance = self.with_core(|core| {
+            let maintenance = core.polls >= EVENT_INTERVAL;
+
+            if maintenance {
+                core.polls = 0;
+            }
+
+            maintenance
+        })?;
+
+        // The core is put back while the events wake the tasks
+        if maintenance {
+            if let Some(mut driver) = self.shared.reactor.try_driver() {
+                driver.turn(Some(Duration::ZERO));
+            }
+        }
+
+        self.with_core(|core| {
+            for class in core.aging.order() {
+                let task = match class {
+                    Priority::Normal => self.next_normal(core, maintenance),
+                    _ => steal(|| self.shared.injector(class).steal()),
+                };
+
+                if let Some(task) = task {
+                    let runnable = Priority::ALL.map(|class| self.is_runnable(core, class));
+                    core.aging.polled(class, runnable);
+                    return Some(task);
+                }
+            }
+
+            None
+        })
+    }
+
+    fn next_normal(&self, core: &mut Core, maintenance: bool) -> Option<RawTask> {
+        if maintenance {
+            if let Some(task) = self.steal_global(core) {
+                return Some(task);
+            }
+        }
+
+        if let Some(task) = core.lifo.take() {
+            if core.lifo_polls < MAX_LIFO_POLLS {
+                core.lifo_polls += 1;
+                return Some(task);
+            }
+
+            core.local.push(task);
+        }
+
+        core.lifo_polls = 0;
+
+        core.local
+            .pop()
+            .or_else(|| self.steal_global(core))
+            .or_else(|| self.steal_others(core))
+    }
+
+    /// Check if the worker could take a task of the class without stealing it
+    /// from the other workers
+    fn is_runnable(&self, core: &Core, class: Priority) -> bool {
+        let local = class == Priority::Normal && (core.lifo.is_some() || !core.local.is_empty());
+        local || !self.shared.injector(class).is_empty()
+    }
+
+    fn steal_global(&self, core: &Core) -> Option<RawTask> {
+        steal(|| {
+            self.shared
+                .injector(Priority::Normal)
+                .steal_batch_and_pop(&core.local)
+        })
+    }
+
+    /// Steal a half of the tasks of another worker, starting from a random one
+    fn steal_others(&self, core: &Core) -> Option<RawTask> {
+        let remotes = self.shared.remotes();
+        let start = fastrand::usize(..remotes.len());
+
+        let task = (0..remotes.len())
+            .map(|i| (start + i) % remotes.len())
+            .filter(|&i| i != core.index)
+            .find_map(|i| steal(|| remotes[i].stealer.steal_batch_and_pop(&core.local)))?;
+
+        remotes[core.index].steals.fetch_add(1, Ordering::Relaxed);
+
+        // Let other sleeping workers help with the rest of the batch
+        if !core.local.is_empty() {
+            self.shared.notify();
+        }
+
+        Some(task)
+    }
+
+    /// Wait until a new task is submitted. One of the sleeping workers polls
+    /// the reactor events meanwhile.
+    fn park(&self) {
+        let shared = &self.shared;
+
+        shared.sleepers.fetch_add(1, Ordering::SeqCst);
+        atomic::fence(Ordering::SeqCst);
+
+        if let Some(mut driver) = shared.reactor.try_driver() {
+            shared.driver_parked.store(true, Ordering::SeqCst);
+            atomic::fence(Ordering::SeqCst);
+
+            if !shared.has_tasks() && !shared.is_shutdown() {
+                self.exec.threads.park(|| driver.turn(None));
+            }
+
+            shared.driver_parked.store(false, Ordering::SeqCst);
+            drop(driver);
+
+            // This worker is going to poll the woken tasks, so another one must
+            // take over polling the events
+            if *shared.waiters.lock() > 0 {
+                shared.cvar.notify_one();
+            }
+        } else {
+            let mut waiters = shared.waiters.lock();
+
+            if !shared.has_tasks() && !shared.is_shutdown() {
+                *waiters += 1;
+                self.exec.threads.park(|| shared.cvar.wait(&mut waiters));
+                *waiters -= 1;
+            }
+        }
+
+        shared.sleepers.fetch_sub(1, Ordering::SeqCst);
+    }
+}
@@ -0,0 +1,85 @@
+use super::{handle::JoinError, BlockOnError, Executor, Scheduler};
+use crate::task::coop;
+use std::{
+    future::Future,
+    panic::{self, AssertUnwindSafe},
+    pin::Pin,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    task::{Context, Poll, Wake, Waker},
+    thread::Thread,
+};
+
+/// Future of the `block_on` call. It's polled in place on the blocked thread,
+/// so it doesn't have to be `Send` or `'static`.
+pub(super) struct Root {
+    inner: Arc<RootWaker>,
+    waker: Waker,
+}
+
+struct RootWaker {
+    /// The future must be polled again
+    woken: AtomicBool,
+    unpark: Unpark,
+}
+
+/// The way to resume the blocked thread
+pub(super) enum Unpark {
+    /// The thread is parked until the future is woken
+    Thread(Thread),
+    /// The thread drives the current thread scheduler or waits for the turn
+    /// to drive it
+    Driver(Thread, Arc<Executor>),
+}
+
+impl Root {
+    pub(super) fn new(unpark: Unpark) -> Self {
+        let inner = Arc::new(RootWaker {
+            woken: AtomicBool::new(true),
+            unpark,
+        });
+
+        let waker = Waker::from(Arc::clone(&inner));
+        Self { inner, waker }
+    }
+
+    /// Poll the future if it was woken. Panic of the future is returned as the
+    /// join error.
+    pub(super) fn poll<F>(&self, fut: Pin<&mut F>) -> Option<Result<F::Output, BlockOnError>>
+    where
+        F: Future,
+    {
+        if !self.inner.woken.swap(false, Ordering::AcqRel) {
+            return None;
+        }
+
+        let mut cx = Context::from_waker(&self.waker);
+
+        match panic::catch_unwind(AssertUnwindSafe(|| coop::budget(|| fut.poll(&mut cx)))) {
+            Ok(Poll::Ready(output)) => Some(Ok(output)),
+            Ok(Poll::Pending) => None,
+            Err(payload) => Some(Err(JoinError::Panic(payload).into())),
+        }
+    }
+}
+
+impl Wake for RootWaker {
+    fn wake(self: Arc<Self>) {
+        self.wake_by_ref();
+    }
+
+    fn wake_by_ref(self: &Arc<Self>) {
+        self.woken.store(true, Ordering::SeqCst);
+
+        match &self.unpark {
+            Unpark::Thread(thread) => thread.unpark(),
+            Unpark::Driver(thread, exec) => {
+                if let Scheduler::CurrentThread(sched) = &exec.scheduler {
+                    sched.unpark(thread, &exec.reactor);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,337 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// The task is queued for execution
+const SCHEDULED: usize = 0b000001;
+/// The task is being polled by a worker
+const RUNNING: usize = 0b000010;
+/// The task was woken while it was being polled
+const NOTIFIED: usize = 0b000100;
+/// The task future has finished and its output has been delivered
+const COMPLETE: usize = 0b001000;
+/// The task was aborted
+const CANCELLED: usize = 0b010000;
+/// The task was cancelled by the runtime shutdown
+const SHUTDOWN: usize = 0b100000;
+
+/// Atomic task state. A task is idle when none of the `SCHEDULED`, `RUNNING` and
+/// `COMPLETE` bits are set.
+///
+/// Only the owner of the `RUNNING` bit can access the task future, so no
+/// worker ever blocks on another worker's poll.
+pub(crate) struct State(AtomicUsize);
+
+/// Copy of the task state at some moment
+#[derive(Clone, Copy)]
+pub(crate) struct Snapshot(usize);
+
+/// Result of releasing the `RUNNING` bit after a pending poll
+pub(crate) enum TransitionToIdle {
+    /// The task waits for a wake
+    Idle,
+    /// The task was woken during the poll and must be submitted for execution
+    Notified,
+    /// The task was cancelled during the poll, the caller still owns the
+    /// `RUNNING` bit and must drop the future
+    Cancelled(Snapshot),
+}
+
+impl Snapshot {
+    pub(crate) fn is_complete(self) -> bool {
+        self.0 & COMPLETE != 0
+    }
+
+    pub(crate) fn is_cancelled(self) -> bool {
+        self.0 & CANCELLED != 0
+    }
+
+    pub(crate) fn is_shutdown(self) -> bool {
+        self.0 & SHUTDOWN != 0
+    }
+
+    fn is_running(self) -> bool {
+        self.0 & RUNNING != 0
+    }
+
+    fn is_idle(self) -> bool {
+        self.0 & (SCHEDULED | RUNNING | COMPLETE) == 0
+    }
+}
+
+impl State {
+    /// Create an idle task state
+    pub(crate) fn new() -> Self {
+        Self(AtomicUsize::new(0))
+    }
+
+    pub(crate) fn load(&self) -> Snapshot {
+        Snapshot(self.0.load(Ordering::Acquire))
+    }
+
+    /// Transition on wake. Returns `true` if the caller must submit the task
+    /// for execution. Waking a running task makes it be polled once again
+    /// after the current poll, redundant wakes are coalesced.
+    pub(crate) fn transition_to_scheduled(&self) -> bool {
+        self.transition_to_notified(0)
+    }
+
+    /// Mark the task as cancelled and schedule it, so the future will be dropped
+    /// by the next poll. Returns `true` if the caller must submit the task for
+    /// execution.
+    pub(crate) fn transition_to_cancelled(&self) -> bool {
+        self.transition_to_notified(CANCELLED)
+    }
+
+    /// Take the `RUNNING` bit before polling the scheduled task. Returns `None`
+    /// if the task was shut down while it was queued.
+    pub(crate) fn transition_to_running(&self) -> Option<Snapshot> {
+        self.try_update(|s| {
+            (s.0 & SCHEDULED != 0).then_some(Snapshot((s.0 & !SCHEDULED) | RUNNING))
+        })
+        .map(|prev| Snapshot((prev.0 & !SCHEDULED) | RUNNING))
+    }
+
+    /// Release the `RUNNING` bit after the future returned `Pending`
+    pub(crate) fn transition_to_idle(&self) -> TransitionToIdle {
+        let prev = self.update(|s| {
+            if s.is_cancelled() {
+                Snapshot(s.0 & !NOTIFIED)
+            } else if s.0 & NOTIFIED != 0 {
+                Snapshot((s.0 & !(RUNNING | NOTIFIED)) | SCHEDULED)
+            } else {
+                Snapshot(s.0 & !RUNNING)
+            }
+        });
+
+        if prev.is_cancelled() {
+            TransitionToIdle::Cancelled(prev)
+        } else if prev.0 & NOTIFIED != 0 {
+            TransitionToIdle::Notified
+        } else {
+            TransitionToIdle::Idle
+        }
+    }
+
+    /// Cancel the task on the runtime shutdown. Returns the snapshot with the
+    /// `RUNNING` bit, if the task was idle or queued and the caller must drop its
+    /// future. A running task is dropped by its worker after the current poll.
+    pub(crate) fn transition_to_shutdown(&self) -> Option<Snapshot> {
+        let prev = self.update(|s| {
+            if s.is_complete() {
+                s
+            } else if s.is_running() {
+                Snapshot(s.0 | CANCELLED | SHUTDOWN)
+            } else {
+                Snapshot((s.0 & !SCHEDULED) | RUNNING | CANCELLED | SHUTDOWN)
+            }
+        });
+
+        (!prev.is_complete() && !prev.is_running()).then_some(Snapshot(
+            (prev.0 & !SCHEDULED) | RUNNING | CANCELLED | SHUTDOWN,
+        ))
+    }
+
+    /// Release the `RUNNING` bit and mark the task as complete
+    pub(crate) fn transition_to_complete(&self) {
+        self.update(|s| Snapshot((s.0 & !(RUNNING | NOTIFIED)) | COMPLETE));
+    }
+
+    fn transition_to_notified(&self, flags: usize) -> bool {
+        self.try_update(|s| {
+            if s.is_complete() {
+                None
+            } else if s.is_running() {
+                Some(Snapshot(s.0 | NOTIFIED | flags))
+            } else if s.is_idle() {
+                Some(Snapshot(s.0 | SCHEDULED | flags))
+            } else if s.0 & flags != flags {
+                // Already scheduled, only the flags must be set
+                Some(Snapshot(s.0 | flags))
+            } else {
+                None
+            }
+        })
+        .is_some_and(Snapshot::is_idle)
+    }
+
+    /// Apply the transition, returning the previous state
+    fn update(&self, mut f: impl FnMut(Snapshot) -> Snapshot) -> Snapshot {
+        self.try_update(|s| Some(f(s)))
+            .expect("unconditional transition")
+    }
+
+    /// Apply the transition if `f` returns `Some`, returning the previous state
+    fn try_update(&self, mut f: impl FnMut(Snapshot) -> Option<Snapshot>) -> Option<Snapshot> {
+        self.0
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |s| {
+                f(Snapshot(s)).map(|s| s.0)
+            })
+            .map(Snapshot)
+            .ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_idle(idle: TransitionToIdle) {
+        assert!(matches!(idle, TransitionToIdle::Idle));
+    }
+
+    #[test]
+    fn poll_cycle() {
+        let state = State::new();
+        assert!(state.load().is_idle());
+
+        assert!(state.transition_to_scheduled());
+        assert!(!state.load().is_idle());
+
+        let snapshot = state.transition_to_running().unwrap();
+        assert!(snapshot.is_running());
+        assert!(state.load().is_running());
+
+        assert_idle(state.transition_to_idle());
+        assert!(state.load().is_idle());
+
+        assert!(state.transition_to_scheduled());
+        state.transition_to_running().unwrap();
+        state.transition_to_complete();
+
+        let snapshot = state.load();
+        assert!(snapshot.is_complete());
+        assert!(!snapshot.is_running());
+        assert!(!snapshot.is_cancelled());
+    }
+
+    #[test]
+    fn redundant_wakes_coalesce() {
+        let state = State::new();
+
+        assert!(state.transition_to_scheduled());
+        assert!(!state.transition_to_scheduled());
+        assert!(!state.transition_to_scheduled());
+
+        state.transition_to_running().unwrap();
+
+        // Scheduled only once, so there is no second entry to run
+        assert!(state.transition_to_running().is_none());
+    }
+
+    #[test]
+    fn wake_while_running_repolls_once() {
+        let state = State::new();
+
+        state.transition_to_scheduled();
+        state.transition_to_running().unwrap();
+
+        assert!(!state.transition_to_scheduled());
+        assert!(!state.transition_to_scheduled());
+        assert!(matches!(
+            state.transition_to_idle(),
+            TransitionToIdle::Notified
+        ));
+
+        state.transition_to_running().unwrap();
+        assert_idle(state.transition_to_idle());
+        assert!(state.transition_to_running().is_none());
+    }
+
+    #[test]
+    fn complete_ignores_wakes() {
+        let state = State::new();
+
+        state.transition_to_scheduled();
+        state.transition_to_running().unwrap();
+        state.transition_to_complete();
+
+        assert!(!state.transition_to_scheduled());
+        assert!(!state.transition_to_cancelled());
+        assert!(state.transition_to_shutdown().is_none());
+        assert!(state.load().is_complete());
+    }
+
+    #[test]
+    fn cancel_idle() {
+        let state = State::new();
+
+        assert!(state.transition_to_cancelled());
+        assert!(!state.transition_to_scheduled());
+
+        let snapshot = state.transition_to_running().unwrap();
+        assert!(snapshot.is_cancelled());
+        assert!(!snapshot.is_shutdown());
+    }
+
+    #[test]
+    fn cancel_scheduled() {
+        let state = State::new();
+
+        state.transition_to_scheduled();
+        assert!(!state.transition_to_cancelled());
+
+        let snapshot = state.transition_to_running().unwrap();
+        assert!(snapshot.is_cancelled());
+    }
+
+    #[test]
+    fn cancel_running() {
+        let state = State::new();
+
+        state.transition_to_scheduled();
+        state.transition_to_running().unwrap();
+
+        assert!(!state.transition_to_cancelled());
+
+        match state.transition_to_idle() {
+            TransitionToIdle::Cancelled(snapshot) => {
+                assert!(snapshot.is_running());
+                assert!(!snapshot.is_shutdown());
+            }
+            _ => panic!("cancellation is lost"),
+        }
+
+        // The poller still owns the task and completes it
+        assert!(state.load().is_running());
+        state.transition_to_complete();
+        assert!(state.load().is_complete());
+    }
+
+    #[test]
+    fn shutdown_idle() {
+        let state = State::new();
+
+        let snapshot = state.transition_to_shutdown().unwrap();
+        assert!(snapshot.is_running());
+        assert!(snapshot.is_cancelled());
+        assert!(snapshot.is_shutdown());
+
+        assert!(!state.transition_to_scheduled());
+        assert!(state.transition_to_shutdown().is_none());
+    }
+
+    #[test]
+    fn shutdown_scheduled() {
+        let state = State::new();
+
+        state.transition_to_scheduled();
+        assert!(state.transition_to_shutdown().is_some());
+
+        // The queued entry must not poll the task
+        assert!(state.transition_to_running().is_none());
+    }
+
+    #[test]
+    fn shutdown_running() {
+        let state = State::new();
+
+        state.transition_to_scheduled();
+        state.transition_to_running().unwrap();
+
+        assert!(state.transition_to_shutdown().is_none());
+
+        match state.transition_to_idle() {
+            TransitionToIdle::Cancelled(snapshot) => assert!(snapshot.is_shutdown()),
+            _ => panic!("shutdown is lost"),
+        }
+    }
+}
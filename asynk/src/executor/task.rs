@@ -1,91 +1,435 @@
-use super::{handle::JoinHandle, Executor};
-use futures::{channel::oneshot, FutureExt};
-use parking_lot::Mutex;
+use super::{
+    blocking::PoolKind,
+    handle::{JoinError, JoinHandle},
+    state::{Snapshot, State, TransitionToIdle},
+    waker, Executor,
+};
+use crate::task::{coop, Id, Priority};
+use futures::channel::oneshot;
 use std::{
+    cell::UnsafeCell,
     future::Future,
-    marker::PhantomData,
+    panic::{self, AssertUnwindSafe, Location},
     pin::Pin,
+    ptr::NonNull,
     sync::Arc,
-    task::{Context, Poll, Wake},
+    task::{Context, Poll},
 };
 
-type TaskFuture<T> = Pin<Box<dyn Future<Output = T> + Send + 'static>>;
+type OutputSender<T> = oneshot::Sender<Result<T, JoinError>>;
+
+/// Task data which doesn't depend on the future type. Wakers point directly
+/// to the header.
+pub(crate) struct Header {
+    pub(crate) state: State,
+    /// Identifier of the task in the runtime task list
+    pub(crate) id: Id,
+    /// Name given to the task with `task::Builder`
+    pub(crate) name: Option<String>,
+    /// Scheduling class of the task
+    pub(crate) priority: Priority,
+    /// Location where the task was spawned
+    pub(crate) location: &'static Location<'static>,
+    vtable: &'static Vtable,
+}
+
+/// Functions operating on the typed task
+struct Vtable {
+    /// Poll the scheduled task
+    poll: unsafe fn(NonNull<Header>),
+    /// Drop the future of the task and complete it with the error. The caller
+    /// must own the `RUNNING` bit.
+    cancel: unsafe fn(NonNull<Header>, JoinError),
+    /// Submit the task for execution, consuming a reference
+    schedule: unsafe fn(NonNull<Header>),
+    /// Increment the reference count
+    clone: unsafe fn(NonNull<Header>),
+    /// Decrement the reference count, deallocating the task if it was the last one
+    drop_ref: unsafe fn(NonNull<Header>),
+}
+
+/// Task allocation. The header must be the first field, so a pointer to the cell
+/// is also a pointer to the header.
+#[repr(C)]
+struct Cell<F: Future, S> {
+    header: Header,
+    /// Scheduler which executes the task
+    scheduler: S,
+    /// Task future. Accessed only by the owner of the `RUNNING` state bit.
+    fut: UnsafeCell<Option<F>>,
+    /// Result channel. Accessed only by the owner of the `RUNNING` state bit.
+    out_tx: UnsafeCell<Option<OutputSender<F::Output>>>,
+}
 
-pub struct Task<T, W> {
-    fut: Mutex<Option<TaskFuture<T>>>,
-    out_tx: Mutex<Option<oneshot::Sender<T>>>,
-    _waker: PhantomData<W>,
+/// Owned reference to a task
+pub(crate) struct RawTask {
+    ptr: NonNull<Header>,
 }
 
-impl<T, W> Task<T, W> {
-    pub fn new(fut: impl Future<Output = T> + Send + 'static) -> (Arc<Self>, JoinHandle<T>)
+// SAFETY: the header is thread safe and the future is accessed only by the thread
+// which owns the `RUNNING` state bit. Futures which are not `Send` are polled and
+// dropped only by their local set thread, other threads only schedule them.
+unsafe impl Send for RawTask {}
+unsafe impl Sync for RawTask {}
+
+impl RawTask {
+    /// Allocate a new idle task. The caller location is recorded as the spawn
+    /// location of the task.
+    #[track_caller]
+    pub(crate) fn new<F, S>(
+        fut: F,
+        scheduler: S,
+        name: Option<String>,
+        priority: Priority,
+    ) -> (Self, JoinHandle<F::Output>)
     where
-        T: Send + 'static,
+        F: Future + 'static,
+        F::Output: 'static,
+        S: Schedule,
     {
-        let fut = Box::pin(fut);
-
         let (tx, rx) = oneshot::channel();
 
-        let task = Arc::new(Task {
-            fut: Mutex::new(Some(Box::pin(fut))),
-            out_tx: Mutex::new(Some(tx)),
-            _waker: PhantomData,
+        let cell = Arc::new(Cell {
+            header: Header {
+                state: State::new(),
+                id: Id::next(),
+                name,
+                priority,
+                location: Location::caller(),
+                vtable: vtable::<F, S>(),
+            },
+            scheduler,
+            fut: UnsafeCell::new(Some(fut)),
+            out_tx: UnsafeCell::new(Some(tx)),
         });
 
-        (task, JoinHandle::new(rx))
+        // SAFETY: `Arc::into_raw` never returns null
+        let ptr = unsafe { NonNull::new_unchecked(Arc::into_raw(cell) as *mut Header) };
+        let task = Self { ptr };
+
+        let jh = JoinHandle::new(rx, task.clone());
+        (task, jh)
     }
 
-    fn ready(&self, output: T) {
-        // If result channel is dropped, then probably task output was taken
-        if let Some(out_tx) = self.out_tx.lock().take() {
-            out_tx.send(output).ok();
+    /// Take ownership of a reference
+    ///
+    /// # Safety
+    ///
+    /// `ptr` must point to a task header and own one reference to the task
+    unsafe fn from_raw(ptr: NonNull<Header>) -> Self {
+        Self { ptr }
+    }
+
+    pub(crate) fn header(&self) -> &Header {
+        // SAFETY: the task is alive while we hold a reference
+        unsafe { self.ptr.as_ref() }
+    }
+
+    /// Poll the scheduled task
+    pub(crate) fn run(self) {
+        // SAFETY: the pointer is valid and the task has been scheduled
+        unsafe { (self.header().vtable.poll)(self.ptr) }
+    }
+
+    /// Schedule the task, if it's not already scheduled or running
+    pub(crate) fn wake(&self) {
+        // SAFETY: the pointer is valid
+        unsafe { wake_by_ref(self.ptr) }
+    }
+
+    /// Mark the task as cancelled and schedule it, so the future will be dropped
+    pub(crate) fn abort(&self) {
+        if self.header().state.transition_to_cancelled() {
+            // SAFETY: the pointer is valid
+            unsafe { schedule(self.ptr) }
+        }
+    }
+
+    /// Cancel the task on the runtime shutdown. The future of an idle or queued
+    /// task is dropped right away, a running task is dropped after its poll.
+    pub(crate) fn shutdown(&self) {
+        if self.header().state.transition_to_shutdown().is_some() {
+            // SAFETY: the pointer is valid and we own the `RUNNING` bit
+            unsafe { (self.header().vtable.cancel)(self.ptr, JoinError::Shutdown) }
+        }
+    }
+
+    /// Mark the idle task as scheduled and return a new reference, which the
+    /// caller submits for execution instead of the scheduler. Returns `None` if
+    /// the task isn't idle.
+    pub(crate) fn try_schedule(&self) -> Option<RawTask> {
+        self.header()
+            .state
+            .transition_to_scheduled()
+            .then(|| self.clone())
+    }
+
+    /// Cancel the scheduled task which can't be executed, completing it with
+    /// the error
+    pub(crate) fn reject(self, err: JoinError) {
+        if self.header().state.transition_to_running().is_some() {
+            // SAFETY: the pointer is valid and we own the `RUNNING` bit
+            unsafe { (self.header().vtable.cancel)(self.ptr, err) }
         }
     }
+
+    /// Check if the task is completed or cancelled
+    pub(crate) fn is_finished(&self) -> bool {
+        self.header().state.load().is_complete()
+    }
+}
+
+impl Clone for RawTask {
+    fn clone(&self) -> Self {
+        // SAFETY: the pointer is valid
+        unsafe { clone_ref(self.ptr) };
+        Self { ptr: self.ptr }
+    }
+}
+
+impl Drop for RawTask {
+    fn drop(&mut self) {
+        // SAFETY: we own this reference
+        unsafe { drop_ref(self.ptr) }
+    }
+}
+
+/// Schedule the task if it's idle
+///
+/// # Safety
+///
+/// `ptr` must point to a live task header
+pub(super) unsafe fn wake_by_ref(ptr: NonNull<Header>) {
+    if ptr.as_ref().state.transition_to_scheduled() {
+        schedule(ptr);
+    }
+}
+
+/// Submit the task for execution with a new reference
+///
+/// # Safety
+///
+/// `ptr` must point to a live task header
+unsafe fn schedule(ptr: NonNull<Header>) {
+    clone_ref(ptr);
+    (ptr.as_ref().vtable.schedule)(ptr);
 }
 
-pub struct SpawnedTaskWaker;
+/// Increment the reference count
+///
+/// # Safety
+///
+/// `ptr` must point to a live task header
+pub(super) unsafe fn clone_ref(ptr: NonNull<Header>) {
+    (ptr.as_ref().vtable.clone)(ptr)
+}
 
-pub struct BlockedOnTaskWaker;
+/// Decrement the reference count
+///
+/// # Safety
+///
+/// `ptr` must point to a live task header and the caller must own a reference
+pub(super) unsafe fn drop_ref(ptr: NonNull<Header>) {
+    (ptr.as_ref().vtable.drop_ref)(ptr)
+}
 
-impl<T> Wake for Task<T, SpawnedTaskWaker>
+fn vtable<F, S>() -> &'static Vtable
 where
-    T: Send + 'static,
+    F: Future + 'static,
+    F::Output: 'static,
+    S: Schedule,
 {
-    fn wake(self: Arc<Self>) {
-        Executor::get().task_tp.spawn(move || {
-            let waker = self.clone().into();
-            let mut cx = Context::from_waker(&waker);
-            let mut lock = self.fut.lock();
-            if let Some(mut fut) = lock.take() {
-                match fut.poll_unpin(&mut cx) {
-                    Poll::Ready(output) => self.ready(output),
-                    Poll::Pending => *lock = Some(fut),
-                };
-            }
-        });
+    &Vtable {
+        poll: poll::<F, S>,
+        cancel: cancel::<F, S>,
+        schedule: schedule_typed::<F, S>,
+        clone: clone_typed::<F, S>,
+        drop_ref: drop_typed::<F, S>,
     }
 }
 
-impl<T> Wake for Task<T, BlockedOnTaskWaker>
+unsafe fn poll<F, S>(ptr: NonNull<Header>)
+where
+    F: Future + 'static,
+    F::Output: 'static,
+    S: Schedule,
+{
+    ptr.cast::<Cell<F, S>>().as_ref().run(ptr)
+}
+
+unsafe fn cancel<F, S>(ptr: NonNull<Header>, err: JoinError)
+where
+    F: Future + 'static,
+    F::Output: 'static,
+    S: Schedule,
+{
+    ptr.cast::<Cell<F, S>>().as_ref().cancel(err)
+}
+
+unsafe fn schedule_typed<F, S>(ptr: NonNull<Header>)
 where
-    T: Send + 'static,
+    F: Future,
+    S: Schedule,
 {
-    fn wake(self: Arc<Self>) {
-        let exec = Executor::get();
-
-        exec.task_tp.spawn(move || {
-            let waker = self.clone().into();
-            let mut cx = Context::from_waker(&waker);
-            let mut lock = self.fut.lock();
-            if let Some(mut fut) = lock.take() {
-                match fut.poll_unpin(&mut cx) {
-                    Poll::Ready(output) => {
-                        self.ready(output);
-                        exec.unpark_blocked_thread();
-                    }
-                    Poll::Pending => *lock = Some(fut),
-                };
+    let cell = ptr.cast::<Cell<F, S>>().as_ref();
+    cell.scheduler.schedule(RawTask::from_raw(ptr))
+}
+
+unsafe fn clone_typed<F, S>(ptr: NonNull<Header>)
+where
+    F: Future,
+{
+    Arc::increment_strong_count(ptr.cast::<Cell<F, S>>().as_ptr())
+}
+
+unsafe fn drop_typed<F, S>(ptr: NonNull<Header>)
+where
+    F: Future,
+{
+    Arc::decrement_strong_count(ptr.cast::<Cell<F, S>>().as_ptr())
+}
+
+impl<F, S> Cell<F, S>
+where
+    F: Future + 'static,
+    F::Output: 'static,
+    S: Schedule,
+{
+    /// Poll the scheduled task future
+    ///
+    /// # Safety
+    ///
+    /// `ptr` must point to this cell and the task must be scheduled
+    unsafe fn run(&self, ptr: NonNull<Header>) {
+        let _id = self.header.id.enter();
+
+        let Some(snapshot) = self.header.state.transition_to_running() else {
+            // The task was shut down while it was queued
+            return;
+        };
+
+        if snapshot.is_cancelled() {
+            self.cancel(cancel_error(snapshot));
+            return;
+        }
+
+        // SAFETY: we own the `RUNNING` bit
+        let fut = &mut *self.fut.get();
+
+        let Some(f) = fut.as_mut() else {
+            unreachable!("task future is polled after completion");
+        };
+
+        // SAFETY: the future is never moved out of the task allocation
+        let f = Pin::new_unchecked(f);
+
+        let waker = waker::waker_ref(ptr);
+        let mut cx = Context::from_waker(&waker);
+
+        // Catch the panic so it doesn't unwind through the worker thread
+        match panic::catch_unwind(AssertUnwindSafe(|| coop::budget(|| f.poll(&mut cx)))) {
+            Ok(Poll::Ready(output)) => match self.drop_future() {
+                Ok(()) => self.complete(Ok(output)),
+                Err(err) => self.complete(Err(err)),
+            },
+            Ok(Poll::Pending) => match self.header.state.transition_to_idle() {
+                TransitionToIdle::Idle => {}
+                // The task was woken while it was polled, so it must be polled again
+                TransitionToIdle::Notified => schedule(ptr),
+                TransitionToIdle::Cancelled(snapshot) => self.cancel(cancel_error(snapshot)),
+            },
+            Err(payload) => {
+                // The future may be left in an inconsistent state, so it can't be polled again.
+                // The panic of its destructor, if any, is dropped in favor of the first one.
+                self.drop_future().ok();
+                self.complete(Err(JoinError::Panic(payload)));
             }
-        });
+        }
+    }
+
+    /// Drop the future before notifying the handle
+    ///
+    /// # Safety
+    ///
+    /// The caller must own the `RUNNING` bit
+    unsafe fn cancel(&self, err: JoinError) {
+        let res = self.drop_future().and(Err(err));
+        self.complete(res);
+    }
+
+    /// Drop the future, catching the panic of its destructor, so it doesn't
+    /// unwind through the worker thread
+    ///
+    /// # Safety
+    ///
+    /// The caller must own the `RUNNING` bit
+    unsafe fn drop_future(&self) -> Result<(), JoinError> {
+        // The future is moved out first, so the slot is empty even if its
+        // destructor panics
+        let fut = (*self.fut.get()).take();
+        panic::catch_unwind(AssertUnwindSafe(|| drop(fut))).map_err(JoinError::Panic)
+    }
+
+    /// # Safety
+    ///
+    /// The caller must own the `RUNNING` bit
+    unsafe fn complete(&self, res: Result<F::Output, JoinError>) {
+        let out_tx = (*self.out_tx.get()).take();
+
+        self.header.state.transition_to_complete();
+
+        // If result channel is dropped, then probably task output was taken
+        if let Some(out_tx) = out_tx {
+            out_tx.send(res).ok();
+        }
+
+        self.scheduler.release(self.header.id);
+    }
+}
+
+fn cancel_error(snapshot: Snapshot) -> JoinError {
+    if snapshot.is_shutdown() {
+        JoinError::Shutdown
+    } else {
+        JoinError::Cancelled
+    }
+}
+
+/// Task scheduling strategy, specific to the way the task was started
+pub(crate) trait Schedule: Send + Sync + 'static {
+    /// Submit the task for execution
+    fn schedule(&self, task: RawTask);
+
+    /// Remove the completed task from the runtime task list
+    fn release(&self, id: Id);
+}
+
+/// Task spawned on the task thread pool
+pub(crate) struct Spawned(pub(crate) Arc<Executor>);
+
+/// Synchronous job executed on the blocking or compute thread pool. Aborting
+/// the job drops it, if it hasn't started yet, a running job can't be
+/// interrupted.
+pub(crate) struct Blocking(pub(crate) Arc<Executor>, pub(crate) PoolKind);
+
+impl Schedule for Spawned {
+    fn schedule(&self, task: RawTask) {
+        self.0.schedule(task);
+    }
+
+    fn release(&self, id: Id) {
+        self.0.owned.remove(id);
+    }
+}
+
+impl Schedule for Blocking {
+    fn schedule(&self, task: RawTask) {
+        if let Err((task, err)) = self.0.pool(self.1).push(task, &self.0) {
+            task.reject(err);
+        }
+    }
+
+    fn release(&self, id: Id) {
+        self.0.owned.remove(id);
     }
 }
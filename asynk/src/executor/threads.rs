@@ -0,0 +1,203 @@
+use super::blocking::PoolKind;
+use core_affinity::CoreId;
+use parking_lot::{Condvar, Mutex};
+use std::{
+    io,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    thread::{self, JoinHandle, Scope, ScopedJoinHandle},
+    time::Instant,
+};
+
+/// Default prefix of the thread names
+const DEFAULT_NAME_PREFIX: &str = "asynk";
+
+/// Callback run on a runtime thread
+pub(crate) type Callback = Arc<dyn Fn() + Send + Sync>;
+
+/// Kind of a thread spawned by the runtime
+#[derive(Clone, Copy)]
+pub(crate) enum ThreadKind {
+    Worker,
+    Blocking,
+    Compute,
+    /// Thread of a thread-per-core runtime
+    Core,
+}
+
+/// Settings of the threads spawned by the runtime
+#[derive(Clone, Default)]
+pub(crate) struct ThreadConfig {
+    /// Threads are named `{prefix}-{kind}-{n}`
+    pub(crate) name_prefix: Option<String>,
+    pub(crate) stack_size: Option<usize>,
+    pub(crate) on_start: Option<Callback>,
+    pub(crate) on_stop: Option<Callback>,
+    /// Called before a worker waits for tasks or events
+    pub(crate) on_park: Option<Callback>,
+    /// Called after a worker wakes up
+    pub(crate) on_unpark: Option<Callback>,
+    /// Cores which the task workers or the threads of a thread-per-core runtime
+    /// are pinned to in turn
+    pub(crate) task_affinity: Vec<CoreId>,
+    /// Cores which the blocking and compute threads are pinned to in turn
+    pub(crate) blocking_affinity: Vec<CoreId>,
+    /// Number of spawned threads of every kind
+    spawned: Arc<[AtomicUsize; 4]>,
+}
+
+impl ThreadConfig {
+    /// Spawn a thread of the kind, running the start and stop callbacks around
+    /// `f`
+    pub(crate) fn spawn(
+        &self,
+        kind: ThreadKind,
+        f: impl FnOnce() + Send + 'static,
+    ) -> io::Result<JoinHandle<()>> {
+        let n = self.spawned[kind as usize].fetch_add(1, Ordering::Relaxed);
+
+        let affinity = match kind {
+            ThreadKind::Worker | ThreadKind::Core => &self.task_affinity,
+            ThreadKind::Blocking | ThreadKind::Compute => &self.blocking_affinity,
+        };
+
+        let core = (!affinity.is_empty()).then(|| affinity[n % affinity.len()]);
+
+        self.builder(kind, n).spawn(self.wrap(core, f))
+    }
+
+    /// Spawn a scoped thread of the kind with the given index, pinned to the
+    /// core, if any
+    pub(crate) fn spawn_scoped<'scope, T>(
+        &self,
+        scope: &'scope Scope<'scope, '_>,
+        kind: ThreadKind,
+        n: usize,
+        core: Option<CoreId>,
+        f: impl FnOnce() -> T + Send + 'scope,
+    ) -> io::Result<ScopedJoinHandle<'scope, T>>
+    where
+        T: Send + 'scope,
+    {
+        self.builder(kind, n)
+            .spawn_scoped(scope, self.wrap(core, f))
+    }
+
+    /// Run `f`, which waits for tasks or events, between the park and unpark
+    /// callbacks
+    pub(crate) fn park<R>(&self, f: impl FnOnce() -> R) -> R {
+        if let Some(on_park) = &self.on_park {
+            on_park();
+        }
+
+        let res = f();
+
+        if let Some(on_unpark) = &self.on_unpark {
+            on_unpark();
+        }
+
+        res
+    }
+
+    fn builder(&self, kind: ThreadKind, n: usize) -> thread::Builder {
+        let prefix = self.name_prefix.as_deref().unwrap_or(DEFAULT_NAME_PREFIX);
+        let builder = thread::Builder::new().name(format!("{prefix}-{}-{n}", kind.name()));
+
+        match self.stack_size {
+            Some(size) => builder.stack_size(size),
+            None => builder,
+        }
+    }
+
+    /// Wrap the thread function to pin the thread and run the start and stop
+    /// callbacks
+    fn wrap<T>(&self, core: Option<CoreId>, f: impl FnOnce() -> T) -> impl FnOnce() -> T {
+        /// Runs the stop callback on drop, even if the thread panics
+        struct Stop(Option<Callback>);
+
+        impl Drop for Stop {
+            fn drop(&mut self) {
+                if let Some(on_stop) = &self.0 {
+                    on_stop();
+                }
+            }
+        }
+
+        let on_start = self.on_start.clone();
+        let on_stop = self.on_stop.clone();
+
+        move || {
+            if let Some(core) = core {
+                core_affinity::set_for_current(core);
+            }
+
+            if let Some(on_start) = on_start {
+                on_start();
+            }
+
+            let _stop = Stop(on_stop);
+            f()
+        }
+    }
+}
+
+impl ThreadKind {
+    fn name(self) -> &'static str {
+        match self {
+            Self::Worker => "worker",
+            Self::Blocking => "blocking",
+            Self::Compute => "compute",
+            Self::Core => "core",
+        }
+    }
+}
+
+impl From<PoolKind> for ThreadKind {
+    fn from(kind: PoolKind) -> Self {
+        match kind {
+            PoolKind::Blocking => Self::Blocking,
+            PoolKind::Compute => Self::Compute,
+        }
+    }
+}
+
+/// Wait until the pool threads exit and join them. The number of alive threads
+/// is read by `alive` under the lock, and `exited` is notified when a thread
+/// exits. Threads are waited for until the deadline, if any, and the ones
+/// which are still running are detached. The current thread isn't waited for,
+/// if it's one of the pool threads.
+pub(crate) fn join_all<T>(
+    handles: Vec<JoinHandle<()>>,
+    lock: &Mutex<T>,
+    exited: &Condvar,
+    alive: impl Fn(&T) -> usize,
+    deadline: Option<Instant>,
+) {
+    let current = thread::current().id();
+    let own = usize::from(handles.iter().any(|h| h.thread().id() == current));
+
+    let mut guard = lock.lock();
+
+    while alive(&guard) > own {
+        match deadline {
+            Some(deadline) => {
+                if exited.wait_until(&mut guard, deadline).timed_out() {
+                    break;
+                }
+            }
+            None => exited.wait(&mut guard),
+        }
+    }
+
+    if alive(&guard) == own {
+        drop(guard);
+
+        for handle in handles {
+            if handle.thread().id() != current {
+                handle.join().ok();
+            }
+        }
+    }
+}
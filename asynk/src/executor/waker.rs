@@ -0,0 +1,42 @@
+use super::task::{self, Header};
+use std::{
+    mem::ManuallyDrop,
+    ptr::NonNull,
+    task::{RawWaker, RawWakerVTable, Waker},
+};
+
+static WAKER_VTABLE: RawWakerVTable =
+    RawWakerVTable::new(clone_waker, wake, wake_by_ref, drop_waker);
+
+/// Create a waker borrowing the task reference of the caller. The waker is
+/// not dropped, so the reference count is left untouched.
+///
+/// # Safety
+///
+/// The task must stay alive while the waker is used
+pub(super) unsafe fn waker_ref(ptr: NonNull<Header>) -> ManuallyDrop<Waker> {
+    ManuallyDrop::new(Waker::from_raw(raw_waker(ptr)))
+}
+
+fn raw_waker(ptr: NonNull<Header>) -> RawWaker {
+    RawWaker::new(ptr.as_ptr() as *const (), &WAKER_VTABLE)
+}
+
+unsafe fn clone_waker(ptr: *const ()) -> RawWaker {
+    let ptr = NonNull::new_unchecked(ptr as *mut Header);
+    task::clone_ref(ptr);
+    raw_waker(ptr)
+}
+
+unsafe fn wake(ptr: *const ()) {
+    wake_by_ref(ptr);
+    drop_waker(ptr);
+}
+
+unsafe fn wake_by_ref(ptr: *const ()) {
+    task::wake_by_ref(NonNull::new_unchecked(ptr as *mut Header))
+}
+
+unsafe fn drop_waker(ptr: *const ()) {
+    task::drop_ref(NonNull::new_unchecked(ptr as *mut Header))
+}
@@ -1,15 +1,25 @@
 pub mod net;
+pub mod task;
 
 mod builder;
 mod executor;
 mod reactor;
+mod runtime;
 
-use executor::Executor;
 use std::future::Future;
 
 pub use {
     builder::AsynkBuilder,
-    executor::{handle::JoinHandle, BlockOnError},
+    executor::{
+        blocking::QueueFullError,
+        handle::{AbortHandle, JoinError, JoinHandle},
+        local::{spawn_local, LocalSet, RunUntil},
+        BlockOnError,
+    },
+    runtime::{
+        EnterGuard, Handle, PoolMetrics, Runtime, RuntimeMetrics, ThreadPerCore, TryCurrentError,
+    },
+    task::{JoinSet, Scope},
 };
 
 /// Runtime builder
@@ -17,26 +27,106 @@ pub fn builder() -> AsynkBuilder {
     AsynkBuilder::new()
 }
 
-/// Block current thread on the provided asynchronous task
-pub fn block_on<T>(fut: impl Future<Output = T> + Send + 'static) -> Result<T, BlockOnError>
+/// Block current thread on the provided future on the current runtime. The
+/// future is polled on the current thread, so it may borrow local data and
+/// doesn't have to be `Send`.
+///
+/// # Panics
+///
+/// Panics if called outside of the runtime context
+pub fn block_on<F>(fut: F) -> Result<F::Output, BlockOnError>
 where
-    T: Send + 'static,
+    F: Future,
+{
+    Handle::current().block_on(fut)
+}
+
+/// Block current thread on the future returned by `f` until it and all tasks
+/// spawned on the scope are finished. The tasks may borrow data of the
+/// enclosing stack frame:
+///
+/// ```no_run
+/// # let rt = asynk::builder().build().unwrap();
+/// # let _enter = rt.enter();
+/// let mut chunks = vec![vec![1, 2], vec![3, 4]];
+/// let chunks = &mut chunks;
+///
+/// asynk::scope(|s| async move {
+///     for chunk in chunks {
+///         s.spawn(async move { chunk.iter_mut().for_each(|x| *x *= 2) });
+///     }
+/// })
+/// .unwrap();
+/// ```
+///
+/// The scope blocks the thread rather than being awaited, because a future
+/// can be leaked while its tasks still use the borrowed data. Like
+/// `block_on`, it fails if the current thread polls runtime tasks.
+///
+/// # Panics
+///
+/// Panics if called outside of the runtime context
+pub fn scope<'env, F, Fut>(f: F) -> Result<Fut::Output, BlockOnError>
+where
+    F: FnOnce(Scope<'env>) -> Fut,
+    Fut: Future,
 {
-    Executor::get().block_on(fut)
+    Handle::current().scope(f)
 }
 
-/// Spawn new asynchronous task
+/// Spawn new asynchronous task on the current runtime
+///
+/// # Panics
+///
+/// Panics if called outside of the runtime context
+#[track_caller]
 pub fn spawn<T>(fut: impl Future<Output = T> + Send + 'static) -> JoinHandle<T>
 where
     T: Send + 'static,
 {
-    Executor::get().spawn(fut)
+    Handle::current().spawn(fut)
+}
+
+/// Spawn synchronous task on dedicated thread pool of the current runtime
+///
+/// # Panics
+///
+/// Panics if called outside of the runtime context
+#[track_caller]
+pub fn spawn_blocking<T>(f: impl FnOnce() -> T + Send + 'static) -> JoinHandle<T>
+where
+    T: Send + 'static,
+{
+    Handle::current().spawn_blocking(f)
+}
+
+/// Spawn CPU-bound synchronous task on the compute thread pool of the current
+/// runtime. The pool has a thread per CPU by default, so heavy computations
+/// neither stall the task workers nor compete with blocking I/O jobs.
+///
+/// # Panics
+///
+/// Panics if called outside of the runtime context
+#[track_caller]
+pub fn spawn_compute<T>(f: impl FnOnce() -> T + Send + 'static) -> JoinHandle<T>
+where
+    T: Send + 'static,
+{
+    Handle::current().spawn_compute(f)
 }
 
-/// Spawn synchronous task on dedicated thread pool
-pub fn spawn_blocking<T>(f: impl Fn() -> T + Send + 'static) -> JoinHandle<T>
+/// Spawn synchronous task on dedicated thread pool of the current runtime,
+/// failing if the queue of the pool is full
+///
+/// # Panics
+///
+/// Panics if called outside of the runtime context
+#[track_caller]
+pub fn try_spawn_blocking<T>(
+    f: impl FnOnce() -> T + Send + 'static,
+) -> Result<JoinHandle<T>, QueueFullError>
 where
     T: Send + 'static,
 {
-    Executor::get().spawn_blocking(f)
+    Handle::current().try_spawn_blocking(f)
 }
@@ -1,9 +1,11 @@
 pub(crate) mod stream;
 
 use super::TcpStream;
-use crate::reactor::non_blocking::{poll_io, NonBlocking};
+use crate::reactor::non_blocking::NonBlocking;
 use futures::Stream;
 use mio::{net::TcpListener as MioTcpListener, Interest};
+#[cfg(unix)]
+use socket2::{Domain, Protocol, Socket, Type};
 use std::{
     io::{self, Result},
     net::SocketAddr,
@@ -28,6 +30,27 @@ impl TcpListener {
         Ok(Self(MioTcpListener::bind(addr)?))
     }
 
+    /// Bind a new TCP listener with the `SO_REUSEPORT` option set. Several
+    /// listeners (e.g. one per core) can be bound to the same address this way
+    /// and the kernel balances incoming connections between them.
+    #[cfg(unix)]
+    pub fn bind_reuse_port(addr: SocketAddr) -> io::Result<Self> {
+        let socket = Socket::new(Domain::for_address(addr), Type::STREAM, Some(Protocol::TCP))?;
+
+        socket.set_reuse_address(true)?;
+        socket.set_reuse_port(true)?;
+        socket.set_nonblocking(true)?;
+        socket.bind(&addr.into())?;
+        socket.listen(1024)?;
+
+        Ok(Self(MioTcpListener::from_std(socket.into())))
+    }
+
+    /// Returns the local socket address of this listener
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.0.local_addr()
+    }
+
     /// Accepts a new `TcpStream`.
     ///
     /// If an accepted stream is returned, the remote address of the peer is
@@ -44,9 +67,7 @@ impl Stream for Accept {
     type Item = Result<(TcpStream, SocketAddr)>;
 
     fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
-        let (stream, addr) = ready!(poll_io(self.0.token(), cx, Interest::READABLE, || self
-            .0
-            .accept()))?;
+        let (stream, addr) = ready!(self.0.poll_io(cx, Interest::READABLE, || self.0.accept()))?;
 
         let non_blocking =
             NonBlocking::try_new(stream, Interest::READABLE.add(Interest::WRITABLE))?;
@@ -1,4 +1,4 @@
-use crate::reactor::non_blocking::{poll_io, NonBlocking};
+use crate::reactor::non_blocking::NonBlocking;
 use futures::future::poll_fn;
 use mio::{net::UdpSocket as MioUdpSocket, Interest};
 use std::{io, net::SocketAddr};
@@ -37,7 +37,7 @@ impl UdpSocket {
     /// Make sure to always use a sufficiently large buffer to hold the
     /// maximum UDP packet size, which can be up to 65536 bytes in size.
     pub async fn recv(&self, buf: &mut [u8]) -> io::Result<usize> {
-        poll_fn(|cx| poll_io(self.0.token(), cx, Interest::READABLE, || self.0.recv(buf))).await
+        poll_fn(|cx| self.0.poll_io(cx, Interest::READABLE, || self.0.recv(buf))).await
     }
 
     /// Receives data from the socket. On success, returns the number of bytes
@@ -52,9 +52,8 @@ impl UdpSocket {
     /// maximum UDP packet size, which can be up to 65536 bytes in size.
     pub async fn recv_from(&self, buf: &mut [u8]) -> io::Result<(usize, SocketAddr)> {
         poll_fn(|cx| {
-            poll_io(self.0.token(), cx, Interest::READABLE, || {
-                self.0.recv_from(buf)
-            })
+            self.0
+                .poll_io(cx, Interest::READABLE, || self.0.recv_from(buf))
         })
         .await
     }
@@ -62,16 +61,15 @@ impl UdpSocket {
     /// Sends data on the socket to the address previously bound via connect(). On success,
     /// returns the number of bytes written.
     pub async fn send(&self, buf: &[u8]) -> io::Result<usize> {
-        poll_fn(|cx| poll_io(self.0.token(), cx, Interest::WRITABLE, || self.0.send(buf))).await
+        poll_fn(|cx| self.0.poll_io(cx, Interest::WRITABLE, || self.0.send(buf))).await
     }
 
     /// Sends data on the socket to the given address. On success, returns the
     /// number of bytes written.
     pub async fn send_to(&self, buf: &[u8], target: SocketAddr) -> io::Result<usize> {
         poll_fn(|cx| {
-            poll_io(self.0.token(), cx, Interest::WRITABLE, || {
-                self.0.send_to(buf, target)
-            })
+            self.0
+                .poll_io(cx, Interest::WRITABLE, || self.0.send_to(buf, target))
         })
         .await
     }
@@ -87,7 +85,7 @@ impl UdpSocket {
     /// Make sure to always use a sufficiently large buffer to hold the
     /// maximum UDP packet size, which can be up to 65536 bytes in size.
     pub async fn peek(&self, buf: &mut [u8]) -> io::Result<usize> {
-        poll_fn(|cx| poll_io(self.0.token(), cx, Interest::READABLE, || self.0.peek(buf))).await
+        poll_fn(|cx| self.0.poll_io(cx, Interest::READABLE, || self.0.peek(buf))).await
     }
 
     /// Receives data from the socket, without removing it from the input queue.
@@ -103,9 +101,8 @@ impl UdpSocket {
     /// maximum UDP packet size, which can be up to 65536 bytes in size.
     pub async fn peek_from(&self, buf: &mut [u8]) -> io::Result<(usize, SocketAddr)> {
         poll_fn(|cx| {
-            poll_io(self.0.token(), cx, Interest::READABLE, || {
-                self.0.peek_from(buf)
-            })
+            self.0
+                .poll_io(cx, Interest::READABLE, || self.0.peek_from(buf))
         })
         .await
     }
@@ -3,50 +3,98 @@ pub mod non_blocking;
 pub(crate) mod waker_map;
 
 use mio::{event::Source, Events, Interest, Poll, Registry, Token};
-use parking_lot::Mutex;
+use parking_lot::{Mutex, MutexGuard};
 use slab::Slab;
 use std::{
-    io::{self, Error, Result},
-    sync::{Arc, OnceLock},
+    io::{self, Error, ErrorKind, Result},
+    mem,
+    sync::atomic::{AtomicBool, AtomicU64, Ordering},
     task::Waker,
-    thread::{self},
+    time::Duration,
 };
 use waker_map::WakerMap;
 
 const READ_INTEREST_IDX: usize = 0;
 const WRITE_INTEREST_IDX: usize = 1;
 
+/// Token of the waker which interrupts polling
+const WAKE_TOKEN: Token = Token(usize::MAX);
+
 /// Reactor polls events from mio and calls wakers interested
-/// by these events
+/// by these events. The reactor has no thread of its own: events are polled by
+/// the runtime threads which have no tasks to poll.
 pub struct Reactor {
-    wakers: Arc<Mutex<Slab<WakerMap>>>,
+    wakers: Mutex<Slab<WakerMap>>,
     registry: Registry,
+    waker: mio::Waker,
+    shutdown: AtomicBool,
+    /// Number of events of the registered sources
+    events: AtomicU64,
+    /// Events poll, taken by the thread which polls events
+    driver: Mutex<Option<Driver>>,
+}
+
+/// Events poll with the buffer for received events
+struct Driver {
+    poll: Poll,
+    events: Events,
 }
 
-static REACTOR: OnceLock<Reactor> = OnceLock::new();
+/// Exclusive access to polling the reactor events
+pub struct DriverGuard<'a> {
+    reactor: &'a Reactor,
+    driver: MutexGuard<'a, Option<Driver>>,
+}
 
 impl Reactor {
     pub fn new() -> Result<Self> {
         let poll = Poll::new()?;
         let registry = poll.registry().try_clone()?;
+        let waker = mio::Waker::new(poll.registry(), WAKE_TOKEN)?;
+
+        let driver = Driver {
+            poll,
+            events: Events::with_capacity(1024),
+        };
+
+        Ok(Self {
+            registry,
+            wakers: Mutex::new(Slab::new()),
+            waker,
+            shutdown: AtomicBool::new(false),
+            events: AtomicU64::new(0),
+            driver: Mutex::new(Some(driver)),
+        })
+    }
 
-        let wakers = Arc::new(Mutex::new(Slab::new()));
-
-        // Spawn poll events thread
-        thread::Builder::new().name("reactor".into()).spawn({
-            let wakers = Arc::clone(&wakers);
-            move || poll_events_loop(wakers, poll)
-        })?;
+    /// Wait for events until the timeout and wake the interested tasks. Returns
+    /// earlier if the reactor is unparked. Blocks while another thread polls
+    /// events.
+    pub fn drive(&self, timeout: Option<Duration>) {
+        self.driver().turn(timeout);
+    }
 
-        Ok(Self { registry, wakers })
+    /// Take the access to polling events, waiting for another thread to
+    /// release it
+    pub fn driver(&self) -> DriverGuard<'_> {
+        DriverGuard {
+            reactor: self,
+            driver: self.driver.lock(),
+        }
     }
 
-    pub fn get() -> &'static Reactor {
-        REACTOR.get().expect("reactor is not set")
+    /// Take the access to polling events, if no other thread polls them
+    pub fn try_driver(&self) -> Option<DriverGuard<'_>> {
+        self.driver.try_lock().map(|driver| DriverGuard {
+            reactor: self,
+            driver,
+        })
     }
 
-    pub fn set_global(self) {
-        REACTOR.set(self).ok();
+    /// Interrupt waiting for events in [`DriverGuard::turn`]. If no thread
+    /// waits for events, the next wait returns right away.
+    pub fn unpark(&self) {
+        self.waker.wake().ok();
     }
 
     /// Register interested events for the given source
@@ -59,8 +107,17 @@ impl Reactor {
     where
         S: Source,
     {
+        if self.shutdown.load(Ordering::Acquire) {
+            return Err(Error::other("reactor is shut down"));
+        }
+
         let token = Token(self.wakers.lock().insert(waker_map));
-        self.registry.register(source, token, interests)?;
+
+        if let Err(e) = self.registry.register(source, token, interests) {
+            self.wakers.lock().remove(token.0);
+            return Err(e);
+        }
+
         Ok(token)
     }
 
@@ -84,19 +141,69 @@ impl Reactor {
         S: Source,
     {
         self.registry.deregister(source)?;
-        self.wakers.lock().remove(token.0);
+        self.wakers.lock().try_remove(token.0);
         Ok(())
     }
+
+    /// Number of events of the registered sources processed so far
+    pub fn events(&self) -> u64 {
+        self.events.load(Ordering::Relaxed)
+    }
+
+    /// Number of the registered sources
+    pub fn sources(&self) -> usize {
+        self.wakers.lock().len()
+    }
+
+    /// Stop polling events and drop the registered wakers
+    pub fn shutdown(&self) {
+        if self.shutdown.swap(true, Ordering::AcqRel) {
+            return;
+        }
+
+        // Wait for the thread which polls events to return
+        self.waker.wake().ok();
+        self.driver.lock().take();
+
+        // Wakers must be dropped outside of the lock, because dropping a task
+        // may deregister its sources
+        let wakers = mem::take(&mut *self.wakers.lock());
+        drop(wakers);
+    }
 }
 
-fn poll_events_loop(waker_map: Arc<Mutex<Slab<WakerMap>>>, mut poll: Poll) {
-    let mut events = Events::with_capacity(1024);
+impl DriverGuard<'_> {
+    /// Wait for events until the timeout and wake the interested tasks.
+    /// Returns earlier if the reactor is unparked. Has no effect after the
+    /// reactor is shut down.
+    pub fn turn(&mut self, timeout: Option<Duration>) {
+        if self.reactor.shutdown.load(Ordering::Acquire) {
+            return;
+        }
 
-    loop {
-        poll.poll(&mut events, None).unwrap();
+        if let Some(driver) = self.driver.as_mut() {
+            let events = driver.turn(&self.reactor.wakers, timeout);
+            self.reactor.events.fetch_add(events, Ordering::Relaxed);
+        }
+    }
+}
+
+impl Driver {
+    /// Poll events once and call the wakers interested by them. Returns the
+    /// number of events of the registered sources.
+    fn turn(&mut self, wakers: &Mutex<Slab<WakerMap>>, timeout: Option<Duration>) -> u64 {
+        if let Err(e) = self.poll.poll(&mut self.events, timeout) {
+            // Interrupted polls are retried by the caller
+            assert_eq!(e.kind(), ErrorKind::Interrupted, "reactor poll failed: {e}");
+            return 0;
+        }
+
+        let mut count = 0;
+
+        for event in self.events.iter() {
+            if let Some(directions) = wakers.lock().get(event.token().into()) {
+                count += 1;
 
-        for event in events.into_iter() {
-            if let Some(directions) = waker_map.lock().get(event.token().into()) {
                 let wakers = directions.wakers();
 
                 // Call waker interested by this event
@@ -113,5 +220,7 @@ fn poll_events_loop(waker_map: Arc<Mutex<Slab<WakerMap>>>, mut poll: Poll) {
                 }
             }
         }
+
+        count
     }
 }
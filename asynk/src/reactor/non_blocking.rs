@@ -1,10 +1,12 @@
 use super::{waker_map::WakerMap, Reactor};
+use crate::{task::coop, Handle};
 use mio::{event::Source, Interest, Token};
 use std::{
     io::{self, ErrorKind, Read, Write},
     ops::Deref,
     pin::Pin,
-    task::{Context, Poll},
+    sync::Arc,
+    task::{ready, Context, Poll},
 };
 
 /// Wrapper for an I/O source with event tracking capabilities for non-blocking reading/writing
@@ -14,6 +16,13 @@ where
 {
     /// Tracked source
     source: S,
+    /// Registration of the source in the reactor
+    registration: Registration,
+}
+
+/// Source registration in the reactor of the runtime it was created in
+struct Registration {
+    reactor: Arc<Reactor>,
     /// Currently registered mio token
     token: Token,
 }
@@ -35,34 +44,61 @@ impl<S> NonBlocking<S>
 where
     S: Source,
 {
+    /// Register the source in the reactor of the current runtime. Fails if
+    /// called outside of the runtime context.
     pub fn try_new(mut source: S, interests: Interest) -> io::Result<Self> {
-        let token = Reactor::get().register(&mut source, interests, WakerMap::new())?;
-        Ok(Self { source, token })
+        let handle = Handle::try_current().map_err(io::Error::other)?;
+        let reactor = Arc::clone(handle.executor().reactor());
+
+        let token = reactor.register(&mut source, interests, WakerMap::new())?;
+
+        Ok(Self {
+            source,
+            registration: Registration { reactor, token },
+        })
     }
 
-    pub fn token(&self) -> Token {
-        self.token
+    /// Try to complete the I/O operation. If the source is not ready, the task
+    /// will be woken when the reactor receives an event with the given interests.
+    /// Completed operations consume the budget of the task, so it yields once
+    /// the budget is exhausted, even if the source is always ready.
+    pub fn poll_io<T>(
+        &self,
+        cx: &mut Context<'_>,
+        interests: Interest,
+        f: impl FnMut() -> io::Result<T>,
+    ) -> Poll<io::Result<T>> {
+        self.registration.poll_io(cx, interests, f)
     }
 
     /// Deregister source
     fn deregister(&mut self) -> io::Result<()> {
-        Reactor::get().deregister(self.token, &mut self.source)
+        let Registration { reactor, token } = &self.registration;
+        reactor.deregister(*token, &mut self.source)
     }
 }
 
-pub fn poll_io<T>(
-    token: Token,
-    cx: &mut Context<'_>,
-    interests: Interest,
-    mut f: impl FnMut() -> io::Result<T>,
-) -> Poll<io::Result<T>> {
-    match f() {
-        Ok(n) => Poll::Ready(Ok(n)),
-        Err(ref e) if e.kind() == ErrorKind::WouldBlock => {
-            Reactor::get().set_waker(token, interests, cx.waker().clone())?;
-            Poll::Pending
+impl Registration {
+    fn poll_io<T>(
+        &self,
+        cx: &mut Context<'_>,
+        interests: Interest,
+        mut f: impl FnMut() -> io::Result<T>,
+    ) -> Poll<io::Result<T>> {
+        ready!(coop::poll_proceed(cx));
+
+        match f() {
+            Ok(n) => {
+                coop::consume();
+                Poll::Ready(Ok(n))
+            }
+            Err(ref e) if e.kind() == ErrorKind::WouldBlock => {
+                self.reactor
+                    .set_waker(self.token, interests, cx.waker().clone())?;
+                Poll::Pending
+            }
+            Err(e) => Poll::Ready(Err(e)),
         }
-        Err(e) => Poll::Ready(Err(e)),
     }
 }
 
@@ -71,11 +107,13 @@ where
     S: Source + Read,
 {
     pub fn poll_read(
-        mut self: Pin<&mut Self>,
+        self: Pin<&mut Self>,
         cx: &mut Context<'_>,
         buf: &mut [u8],
     ) -> Poll<io::Result<usize>> {
-        poll_io(self.token, cx, Interest::READABLE, || self.source.read(buf))
+        let this = self.get_mut();
+        this.registration
+            .poll_io(cx, Interest::READABLE, || this.source.read(buf))
     }
 }
 
@@ -84,17 +122,19 @@ where
     S: Source + Write,
 {
     pub fn poll_write(
-        mut self: Pin<&mut Self>,
+        self: Pin<&mut Self>,
         cx: &mut Context<'_>,
         buf: &[u8],
     ) -> Poll<io::Result<usize>> {
-        poll_io(self.token, cx, Interest::WRITABLE, || {
-            self.source.write(buf)
-        })
+        let this = self.get_mut();
+        this.registration
+            .poll_io(cx, Interest::WRITABLE, || this.source.write(buf))
     }
 
-    pub fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
-        poll_io(self.token, cx, Interest::WRITABLE, || self.source.flush())
+    pub fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        this.registration
+            .poll_io(cx, Interest::WRITABLE, || this.source.flush())
     }
 }
 
@@ -0,0 +1,84 @@
+use crate::executor::Executor;
+use std::{
+    cell::{Cell, RefCell},
+    marker::PhantomData,
+    sync::Arc,
+};
+
+thread_local! {
+    /// Runtime which the current thread belongs to
+    static CURRENT: RefCell<Option<Arc<Executor>>> = const { RefCell::new(None) };
+
+    /// The current thread polls tasks of some runtime, so it must not block on
+    /// another future
+    static IN_RUNTIME: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Call `f` with the executor of the current runtime, if any
+pub(crate) fn with_current<R>(f: impl FnOnce(&Arc<Executor>) -> R) -> Option<R> {
+    CURRENT.with_borrow(|current| current.as_ref().map(f))
+}
+
+/// Set the runtime of the current thread until the guard is dropped
+pub(crate) fn enter(exec: Arc<Executor>) -> EnterGuard {
+    let prev = CURRENT.with_borrow_mut(|current| current.replace(exec));
+
+    EnterGuard {
+        prev,
+        _not_send: PhantomData,
+    }
+}
+
+/// Restores the previous runtime of the thread on drop
+pub(crate) struct EnterGuard {
+    prev: Option<Arc<Executor>>,
+    /// The guard must be dropped on the thread where it was created
+    _not_send: PhantomData<*const ()>,
+}
+
+impl Drop for EnterGuard {
+    fn drop(&mut self) {
+        let prev = self.prev.take();
+        CURRENT.with_borrow_mut(|current| *current = prev);
+    }
+}
+
+/// Mark the current thread as polling runtime tasks until the guard is dropped.
+/// Returns `None` if the thread is already marked.
+pub(crate) fn enter_runtime() -> Option<RuntimeGuard> {
+    if IN_RUNTIME.replace(true) {
+        return None;
+    }
+
+    Some(RuntimeGuard {
+        _not_send: PhantomData,
+    })
+}
+
+/// Clears the runtime mark of the thread on drop
+pub(crate) struct RuntimeGuard {
+    /// The guard must be dropped on the thread where it was created
+    _not_send: PhantomData<*const ()>,
+}
+
+impl Drop for RuntimeGuard {
+    fn drop(&mut self) {
+        IN_RUNTIME.set(false);
+    }
+}
+
+/// Clear the runtime mark of the thread while `f` is running, so it may block
+/// on futures
+pub(crate) fn exit_runtime<R>(f: impl FnOnce() -> R) -> R {
+    /// Restores the runtime mark of the thread on drop, even if `f` panics
+    struct Reset(bool);
+
+    impl Drop for Reset {
+        fn drop(&mut self) {
+            IN_RUNTIME.set(self.0);
+        }
+    }
+
+    let _reset = Reset(IN_RUNTIME.replace(false));
+    f()
+}
@@ -0,0 +1,127 @@
+use super::context;
+use crate::{
+    executor::Executor,
+    task::{scope, Priority, Scope},
+    BlockOnError, JoinHandle, QueueFullError, RuntimeMetrics,
+};
+use std::{future::Future, marker::PhantomData, sync::Arc};
+
+/// Cloneable handle to a runtime. It can be passed to threads which are not
+/// owned by the runtime to spawn tasks on it.
+#[derive(Clone)]
+pub struct Handle {
+    exec: Arc<Executor>,
+}
+
+/// Error returned by `Handle::try_current` when the thread is not in a runtime context
+#[derive(Debug, thiserror::Error)]
+#[error("there is no asynk runtime in the context of the current thread")]
+pub struct TryCurrentError;
+
+impl Handle {
+    pub(crate) fn new(exec: Arc<Executor>) -> Self {
+        Self { exec }
+    }
+
+    /// Handle to the runtime of the current thread. Runtime worker threads are
+    /// always in the runtime context, other threads can enter it with
+    /// `Handle::enter`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if called outside of the runtime context
+    pub fn current() -> Self {
+        Self::try_current().expect("must be called from the context of an asynk runtime")
+    }
+
+    /// Handle to the runtime of the current thread, if any
+    pub fn try_current() -> Result<Self, TryCurrentError> {
+        context::with_current(|exec| Self::new(Arc::clone(exec))).ok_or(TryCurrentError)
+    }
+
+    /// Enter the runtime context on the current thread, so `Handle::current`,
+    /// `asynk::spawn` and I/O types use this runtime until the guard is dropped
+    pub fn enter(&self) -> EnterGuard<'_> {
+        EnterGuard {
+            _guard: context::enter(Arc::clone(&self.exec)),
+            _handle: PhantomData,
+        }
+    }
+
+    /// Snapshot of the runtime counters and gauges
+    pub fn metrics(&self) -> RuntimeMetrics {
+        self.exec.metrics()
+    }
+
+    /// Block current thread on the provided future. The future is polled on
+    /// the current thread, so it may borrow local data and doesn't have to be
+    /// `Send`.
+    pub fn block_on<F>(&self, fut: F) -> Result<F::Output, BlockOnError>
+    where
+        F: Future,
+    {
+        self.exec.block_on(fut)
+    }
+
+    /// Block current thread on the future returned by `f` until it and all
+    /// tasks spawned on the scope are finished, see [`crate::scope`]
+    pub fn scope<'env, F, Fut>(&self, f: F) -> Result<Fut::Output, BlockOnError>
+    where
+        F: FnOnce(Scope<'env>) -> Fut,
+        Fut: Future,
+    {
+        scope::scope(&self.exec, f)
+    }
+
+    /// Spawn new asynchronous task
+    #[track_caller]
+    pub fn spawn<T>(&self, fut: impl Future<Output = T> + Send + 'static) -> JoinHandle<T>
+    where
+        T: Send + 'static,
+    {
+        self.exec.spawn(fut, None, Priority::Normal)
+    }
+
+    /// Spawn synchronous task on dedicated thread pool
+    #[track_caller]
+    pub fn spawn_blocking<T>(&self, f: impl FnOnce() -> T + Send + 'static) -> JoinHandle<T>
+    where
+        T: Send + 'static,
+    {
+        self.exec.spawn_blocking(f, None)
+    }
+
+    /// Spawn CPU-bound synchronous task on dedicated thread pool, see
+    /// [`crate::spawn_compute`]
+    #[track_caller]
+    pub fn spawn_compute<T>(&self, f: impl FnOnce() -> T + Send + 'static) -> JoinHandle<T>
+    where
+        T: Send + 'static,
+    {
+        self.exec.spawn_compute(f, None)
+    }
+
+    /// Spawn synchronous task on dedicated thread pool, failing if the queue
+    /// of the pool is full
+    #[track_caller]
+    pub fn try_spawn_blocking<T>(
+        &self,
+        f: impl FnOnce() -> T + Send + 'static,
+    ) -> Result<JoinHandle<T>, QueueFullError>
+    where
+        T: Send + 'static,
+    {
+        self.exec.try_spawn_blocking(f, None)
+    }
+
+    pub(crate) fn executor(&self) -> &Arc<Executor> {
+        &self.exec
+    }
+}
+
+/// Guard of the runtime context entered by `Handle::enter`. The previous
+/// context is restored when the guard is dropped.
+pub struct EnterGuard<'a> {
+    _guard: context::EnterGuard,
+    _handle: PhantomData<&'a Handle>,
+}
@@ -0,0 +1,40 @@
+/// Snapshot of the runtime counters and gauges, see [`super::Runtime::metrics`]
+#[derive(Debug, Clone, Default)]
+#[non_exhaustive]
+pub struct RuntimeMetrics {
+    /// Number of task worker threads, zero for a current-thread runtime
+    pub workers: usize,
+    /// Number of spawned tasks which are not finished yet
+    pub alive_tasks: usize,
+    /// Total number of tasks spawned on the runtime, including the synchronous
+    /// ones
+    pub spawned_tasks: u64,
+    /// Total number of finished tasks, including the cancelled ones
+    pub completed_tasks: u64,
+    /// Total number of task polls
+    pub polls: u64,
+    /// Total number of times the workers stole tasks from each other
+    pub steals: u64,
+    /// Number of tasks in the local queue of every worker
+    pub worker_queue_depths: Vec<usize>,
+    /// Number of tasks in the global queues
+    pub global_queue_depth: usize,
+    pub blocking: PoolMetrics,
+    pub compute: PoolMetrics,
+    /// Total number of I/O events processed by the reactor
+    pub io_events: u64,
+    /// Number of I/O sources registered in the reactor
+    pub io_sources: usize,
+}
+
+/// Gauges of the blocking or compute thread pool
+#[derive(Debug, Clone, Default)]
+#[non_exhaustive]
+pub struct PoolMetrics {
+    /// Number of running threads
+    pub threads: usize,
+    /// Number of threads which run a job
+    pub busy_threads: usize,
+    /// Number of jobs waiting for a free thread
+    pub queue_depth: usize,
+}
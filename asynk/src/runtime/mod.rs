@@ -0,0 +1,136 @@
+pub(crate) mod context;
+
+mod handle;
+mod metrics;
+mod thread_per_core;
+
+pub use handle::{EnterGuard, Handle, TryCurrentError};
+pub use metrics::{PoolMetrics, RuntimeMetrics};
+pub use thread_per_core::ThreadPerCore;
+
+use crate::{executor::Executor, task::Scope, BlockOnError, JoinHandle, QueueFullError};
+use std::{
+    future::Future,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+/// Asynchronous runtime: task and blocking thread pools with an I/O reactor.
+///
+/// Several runtimes may exist in one process. Dropping the runtime cancels the
+/// alive tasks and waits for its threads to stop, including the running
+/// blocking jobs.
+pub struct Runtime {
+    handle: Handle,
+}
+
+impl Runtime {
+    pub(crate) fn new(exec: Arc<Executor>) -> Self {
+        Self {
+            handle: Handle::new(exec),
+        }
+    }
+
+    /// Handle to this runtime
+    pub fn handle(&self) -> &Handle {
+        &self.handle
+    }
+
+    /// Enter the runtime context on the current thread
+    pub fn enter(&self) -> EnterGuard<'_> {
+        self.handle.enter()
+    }
+
+    /// Snapshot of the runtime counters and gauges
+    pub fn metrics(&self) -> RuntimeMetrics {
+        self.handle.metrics()
+    }
+
+    /// Block current thread on the provided future. The future is polled on
+    /// the current thread, so it may borrow local data and doesn't have to be
+    /// `Send`.
+    pub fn block_on<F>(&self, fut: F) -> Result<F::Output, BlockOnError>
+    where
+        F: Future,
+    {
+        self.handle.block_on(fut)
+    }
+
+    /// Block current thread on the future returned by `f` until it and all
+    /// tasks spawned on the scope are finished, see [`crate::scope`]
+    pub fn scope<'env, F, Fut>(&self, f: F) -> Result<Fut::Output, BlockOnError>
+    where
+        F: FnOnce(Scope<'env>) -> Fut,
+        Fut: Future,
+    {
+        self.handle.scope(f)
+    }
+
+    /// Spawn new asynchronous task
+    #[track_caller]
+    pub fn spawn<T>(&self, fut: impl Future<Output = T> + Send + 'static) -> JoinHandle<T>
+    where
+        T: Send + 'static,
+    {
+        self.handle.spawn(fut)
+    }
+
+    /// Spawn synchronous task on dedicated thread pool
+    #[track_caller]
+    pub fn spawn_blocking<T>(&self, f: impl FnOnce() -> T + Send + 'static) -> JoinHandle<T>
+    where
+        T: Send + 'static,
+    {
+        self.handle.spawn_blocking(f)
+    }
+
+    /// Spawn CPU-bound synchronous task on dedicated thread pool, see
+    /// [`crate::spawn_compute`]
+    #[track_caller]
+    pub fn spawn_compute<T>(&self, f: impl FnOnce() -> T + Send + 'static) -> JoinHandle<T>
+    where
+        T: Send + 'static,
+    {
+        self.handle.spawn_compute(f)
+    }
+
+    /// Spawn synchronous task on dedicated thread pool, failing if the queue
+    /// of the pool is full
+    #[track_caller]
+    pub fn try_spawn_blocking<T>(
+        &self,
+        f: impl FnOnce() -> T + Send + 'static,
+    ) -> Result<JoinHandle<T>, QueueFullError>
+    where
+        T: Send + 'static,
+    {
+        self.handle.try_spawn_blocking(f)
+    }
+
+    /// Shut the runtime down, giving the alive tasks up to `timeout` to complete.
+    ///
+    /// New tasks are not accepted: their handles return `JoinError::Shutdown`.
+    /// After the timeout the remaining tasks are cancelled with the same error
+    /// and the runtime threads are stopped. The threads are given another
+    /// `timeout` to stop, the ones which are still busy after it (e.g. running
+    /// blocking jobs) are detached.
+    pub fn shutdown_timeout(self, timeout: Duration) {
+        self.handle
+            .executor()
+            .shutdown(Instant::now() + timeout, Some(timeout));
+    }
+
+    /// Shut the runtime down without waiting: alive tasks are cancelled and
+    /// the runtime threads are stopped in background
+    pub fn shutdown_background(self) {
+        self.handle
+            .executor()
+            .shutdown(Instant::now(), Some(Duration::ZERO));
+    }
+}
+
+impl Drop for Runtime {
+    fn drop(&mut self) {
+        self.handle.executor().shutdown(Instant::now(), None);
+    }
+}
@@ -0,0 +1,121 @@
+use super::{Handle, Runtime};
+use crate::{
+    executor::{ThreadConfig, ThreadKind},
+    BlockOnError, LocalSet,
+};
+use core_affinity::CoreId;
+use futures::future::{AbortHandle, Abortable};
+use std::{future::Future, io, thread};
+
+/// Shared-nothing runtime: every core has its own current thread runtime with
+/// its own reactor and local set. Tasks never move between cores, so there are
+/// no cross-core wakeups.
+///
+/// Listeners bound with [`crate::net::TcpListener::bind_reuse_port`] on every
+/// core let the kernel balance connections between them.
+pub struct ThreadPerCore {
+    cores: Vec<Core>,
+    /// Settings of the core threads
+    threads: ThreadConfig,
+}
+
+struct Core {
+    /// Core the thread is pinned to, if affinity is supported
+    id: Option<CoreId>,
+    rt: Runtime,
+}
+
+impl ThreadPerCore {
+    /// Build `count` runtimes. The threads are pinned to the task affinity
+    /// cores in turn, or to all cores in order if it's not set.
+    pub(crate) fn new(
+        count: usize,
+        threads: ThreadConfig,
+        mut build: impl FnMut() -> io::Result<Runtime>,
+    ) -> io::Result<Self> {
+        let affinity = &threads.task_affinity;
+        let all = core_affinity::get_core_ids().unwrap_or_default();
+
+        let cores = (0..count)
+            .map(|idx| {
+                let id = if affinity.is_empty() {
+                    all.get(idx).copied()
+                } else {
+                    Some(affinity[idx % affinity.len()])
+                };
+
+                Ok(Core { id, rt: build()? })
+            })
+            .collect::<io::Result<_>>()?;
+
+        Ok(Self { cores, threads })
+    }
+
+    /// Number of cores the runtime runs on
+    pub fn cores(&self) -> usize {
+        self.cores.len()
+    }
+
+    /// Handle to the runtime of the core. Tasks spawned with it are polled only
+    /// on that core.
+    pub fn handle(&self, core: usize) -> Option<&Handle> {
+        self.cores.get(core).map(|core| core.rt.handle())
+    }
+
+    /// Block on the future created by `f` on every core and return the outputs
+    /// in the order of cores. The future gets the index of its core and is
+    /// polled on the pinned core thread together with the tasks spawned by
+    /// [`crate::spawn`] and [`crate::spawn_local`], so it doesn't have to be
+    /// `Send`.
+    ///
+    /// Fails if a core thread can't be spawned. The futures of the cores which
+    /// are already started are dropped then, and their threads are waited for.
+    pub fn block_on_all<F, Fut>(&self, f: F) -> io::Result<Vec<Result<Fut::Output, BlockOnError>>>
+    where
+        F: Fn(usize) -> Fut + Sync,
+        Fut: Future,
+        Fut::Output: Send,
+    {
+        thread::scope(|s| {
+            let mut threads = Vec::with_capacity(self.cores.len());
+            let mut aborts = Vec::with_capacity(self.cores.len());
+
+            for (idx, core) in self.cores.iter().enumerate() {
+                let f = &f;
+                let (abort, registration) = AbortHandle::new_pair();
+
+                let thread =
+                    self.threads
+                        .spawn_scoped(s, ThreadKind::Core, idx, core.id, move || {
+                            let local = LocalSet::new();
+                            local.block_on(&core.rt, Abortable::new(f(idx), registration))
+                        });
+
+                match thread {
+                    Ok(thread) => {
+                        threads.push(thread);
+                        aborts.push(abort);
+                    }
+                    Err(e) => {
+                        // The scope would wait for the started cores forever
+                        aborts.iter().for_each(AbortHandle::abort);
+                        return Err(e);
+                    }
+                }
+            }
+
+            let outputs = threads
+                .into_iter()
+                .map(|thread| {
+                    let res = thread
+                        .join()
+                        .unwrap_or_else(|e| std::panic::resume_unwind(e));
+
+                    res.map(|out| out.expect("core future is aborted only on a spawn error"))
+                })
+                .collect();
+
+            Ok(outputs)
+        })
+    }
+}
@@ -0,0 +1,25 @@
+use crate::executor::pool;
+
+/// Run the blocking function on the current thread without stalling the other
+/// tasks of the runtime. If the thread is a worker of a multi-thread runtime,
+/// its queued tasks are handed over to a new worker thread first, and `f` may
+/// block on futures with [`crate::Handle::block_on`].
+///
+/// On other threads, e.g. the thread of a current-thread runtime or a local
+/// set, `f` is just called, blocking the tasks driven by the thread.
+///
+/// ```
+/// # use std::time::Duration;
+/// # let rt = asynk::builder().build().unwrap();
+/// rt.block_on(async {
+///     let jh = asynk::spawn(async {
+///         asynk::task::block_in_place(|| std::thread::sleep(Duration::from_millis(10)))
+///     });
+///
+///     jh.await.unwrap();
+/// })
+/// .unwrap();
+/// ```
+pub fn block_in_place<R>(f: impl FnOnce() -> R) -> R {
+    pool::block_in_place(f)
+}
@@ -0,0 +1,108 @@
+use super::Priority;
+use crate::{Handle, JoinHandle};
+use std::future::Future;
+
+/// Builder of a task with custom options. The name and the spawn location of
+/// the task are shown in the `Debug` output of its handles.
+#[derive(Debug, Default)]
+pub struct Builder<'a> {
+    name: Option<&'a str>,
+    priority: Priority,
+}
+
+impl<'a> Builder<'a> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Name of the task
+    pub fn name(mut self, name: &'a str) -> Self {
+        self.name = Some(name);
+        self
+    }
+
+    /// Scheduling class of the task, `Priority::Normal` by default. Ignored by
+    /// `spawn_local` and the synchronous tasks.
+    pub fn priority(mut self, priority: Priority) -> Self {
+        self.priority = priority;
+        self
+    }
+
+    /// Spawn the task on the current runtime
+    ///
+    /// # Panics
+    ///
+    /// Panics if called outside of the runtime context
+    #[track_caller]
+    pub fn spawn<T>(self, fut: impl Future<Output = T> + Send + 'static) -> JoinHandle<T>
+    where
+        T: Send + 'static,
+    {
+        self.spawn_on(fut, &Handle::current())
+    }
+
+    /// Spawn the task on the runtime of the handle
+    #[track_caller]
+    pub fn spawn_on<T>(
+        self,
+        fut: impl Future<Output = T> + Send + 'static,
+        handle: &Handle,
+    ) -> JoinHandle<T>
+    where
+        T: Send + 'static,
+    {
+        handle
+            .executor()
+            .spawn(fut, self.owned_name(), self.priority)
+    }
+
+    /// Spawn the task on the local set driven by the current thread
+    ///
+    /// # Panics
+    ///
+    /// Panics if called outside of a local set
+    #[track_caller]
+    pub fn spawn_local<F>(self, fut: F) -> JoinHandle<F::Output>
+    where
+        F: Future + 'static,
+        F::Output: 'static,
+    {
+        crate::executor::local::spawn_named(fut, self.owned_name())
+    }
+
+    /// Spawn the synchronous task on the blocking thread pool of the current
+    /// runtime
+    ///
+    /// # Panics
+    ///
+    /// Panics if called outside of the runtime context
+    #[track_caller]
+    pub fn spawn_blocking<T>(self, f: impl FnOnce() -> T + Send + 'static) -> JoinHandle<T>
+    where
+        T: Send + 'static,
+    {
+        Handle::current()
+            .executor()
+            .spawn_blocking(f, self.owned_name())
+    }
+
+    /// Spawn the CPU-bound synchronous task on the compute thread pool of the
+    /// current runtime
+    ///
+    /// # Panics
+    ///
+    /// Panics if called outside of the runtime context
+    #[track_caller]
+    pub fn spawn_compute<T>(self, f: impl FnOnce() -> T + Send + 'static) -> JoinHandle<T>
+    where
+        T: Send + 'static,
+    {
+        Handle::current()
+            .executor()
+            .spawn_compute(f, self.owned_name())
+    }
+
+    fn owned_name(&self) -> Option<String> {
+        self.name.map(String::from)
+    }
+}
@@ -0,0 +1,57 @@
+use std::{
+    cell::Cell,
+    task::{Context, Poll},
+};
+
+/// Number of I/O operations a task can complete in one poll before it's forced
+/// to yield
+const INITIAL: u8 = 128;
+
+thread_local! {
+    /// Budget of the task being polled. `None` means the task is unconstrained.
+    static BUDGET: Cell<Option<u8>> = const { Cell::new(None) };
+}
+
+/// Run `f` with a fresh budget, e.g. to poll a task. The previous budget is
+/// restored afterwards.
+pub(crate) fn budget<R>(f: impl FnOnce() -> R) -> R {
+    with_budget(Some(INITIAL), f)
+}
+
+/// Run `f` without a budget
+pub(crate) fn unconstrained<R>(f: impl FnOnce() -> R) -> R {
+    with_budget(None, f)
+}
+
+fn with_budget<R>(budget: Option<u8>, f: impl FnOnce() -> R) -> R {
+    /// Restores the previous budget on drop, even if `f` panics
+    struct Reset(Option<u8>);
+
+    impl Drop for Reset {
+        fn drop(&mut self) {
+            BUDGET.set(self.0);
+        }
+    }
+
+    let _reset = Reset(BUDGET.replace(budget));
+    f()
+}
+
+/// Check if the task may perform one more operation. If the budget is
+/// exhausted, the task is woken to be polled again after the others.
+pub(crate) fn poll_proceed(cx: &mut Context<'_>) -> Poll<()> {
+    match BUDGET.get() {
+        Some(0) => {
+            cx.waker().wake_by_ref();
+            Poll::Pending
+        }
+        _ => Poll::Ready(()),
+    }
+}
+
+/// Consume a unit of the budget for the completed operation
+pub(crate) fn consume() {
+    if let Some(n) = BUDGET.get() {
+        BUDGET.set(Some(n.saturating_sub(1)));
+    }
+}
@@ -0,0 +1,63 @@
+use std::{
+    cell::Cell,
+    fmt,
+    num::NonZeroU64,
+    sync::atomic::{AtomicU64, Ordering},
+};
+
+/// Source of unique task identifiers
+static NEXT_ID: AtomicU64 = AtomicU64::new(1);
+
+thread_local! {
+    /// Task being polled on the current thread
+    static CURRENT: Cell<Option<Id>> = const { Cell::new(None) };
+}
+
+/// Identifier of a task, unique among all tasks of the process
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Id(NonZeroU64);
+
+impl Id {
+    pub(crate) fn next() -> Self {
+        let id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+        Self(NonZeroU64::new(id).expect("task id overflow"))
+    }
+
+    /// Make the task current for the thread until the guard is dropped
+    pub(crate) fn enter(self) -> IdGuard {
+        IdGuard(CURRENT.replace(Some(self)))
+    }
+
+    pub fn as_u64(&self) -> u64 {
+        self.0.get()
+    }
+}
+
+impl fmt::Display for Id {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+/// Restores the previous task of the thread on drop
+pub(crate) struct IdGuard(Option<Id>);
+
+impl Drop for IdGuard {
+    fn drop(&mut self) {
+        CURRENT.set(self.0);
+    }
+}
+
+/// Identifier of the task being polled
+///
+/// # Panics
+///
+/// Panics if called outside of a task
+pub fn id() -> Id {
+    try_id().expect("task::id must be called from a task")
+}
+
+/// Identifier of the task being polled, if any
+pub fn try_id() -> Option<Id> {
+    CURRENT.get()
+}
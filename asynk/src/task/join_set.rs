@@ -0,0 +1,124 @@
+use crate::{AbortHandle, Handle, JoinError, JoinHandle};
+use futures::{stream::FuturesUnordered, FutureExt, StreamExt};
+use std::{
+    fmt,
+    future::{poll_fn, Future},
+    task::{Context, Poll},
+};
+
+/// Set of spawned tasks which can be awaited in the order of completion. All
+/// tasks which are still in the set are aborted when it's dropped.
+pub struct JoinSet<T> {
+    tasks: FuturesUnordered<JoinHandle<T>>,
+}
+
+impl<T> JoinSet<T> {
+    pub fn new() -> Self {
+        Self {
+            tasks: FuturesUnordered::new(),
+        }
+    }
+
+    /// Number of tasks in the set, including the finished ones which are not
+    /// joined yet
+    pub fn len(&self) -> usize {
+        self.tasks.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tasks.is_empty()
+    }
+
+    /// Add the task of the handle to the set
+    pub fn insert(&mut self, jh: JoinHandle<T>) -> AbortHandle {
+        let abort = jh.abort_handle();
+        self.tasks.push(jh);
+        abort
+    }
+
+    /// Spawn the task on the current runtime and add it to the set
+    ///
+    /// # Panics
+    ///
+    /// Panics if called outside of the runtime context
+    #[track_caller]
+    pub fn spawn(&mut self, fut: impl Future<Output = T> + Send + 'static) -> AbortHandle
+    where
+        T: Send + 'static,
+    {
+        self.insert(crate::spawn(fut))
+    }
+
+    /// Spawn the task on the runtime of the handle and add it to the set
+    #[track_caller]
+    pub fn spawn_on(
+        &mut self,
+        fut: impl Future<Output = T> + Send + 'static,
+        handle: &Handle,
+    ) -> AbortHandle
+    where
+        T: Send + 'static,
+    {
+        self.insert(handle.spawn(fut))
+    }
+
+    /// Spawn the task on the local set driven by the current thread and add it
+    /// to the set
+    ///
+    /// # Panics
+    ///
+    /// Panics if called outside of a local set
+    #[track_caller]
+    pub fn spawn_local(&mut self, fut: impl Future<Output = T> + 'static) -> AbortHandle
+    where
+        T: 'static,
+    {
+        self.insert(crate::spawn_local(fut))
+    }
+
+    /// Wait for any of the tasks to finish and remove it from the set. Returns
+    /// `None` if the set is empty.
+    pub async fn join_next(&mut self) -> Option<Result<T, JoinError>> {
+        poll_fn(|cx| self.poll_join_next(cx)).await
+    }
+
+    /// Poll for any of the tasks to finish, removing it from the set
+    pub fn poll_join_next(&mut self, cx: &mut Context<'_>) -> Poll<Option<Result<T, JoinError>>> {
+        self.tasks.poll_next_unpin(cx)
+    }
+
+    /// Remove a finished task from the set without waiting, if there is one
+    pub fn try_join_next(&mut self) -> Option<Result<T, JoinError>> {
+        self.tasks.next().now_or_never().flatten()
+    }
+
+    /// Abort all tasks of the set. They stay in the set and are joined with
+    /// `JoinError::Cancelled`, unless they have already completed.
+    pub fn abort_all(&mut self) {
+        self.tasks.iter().for_each(JoinHandle::abort);
+    }
+
+    /// Abort all tasks and wait for them to finish, leaving the set empty
+    pub async fn shutdown(&mut self) {
+        self.abort_all();
+        while self.join_next().await.is_some() {}
+    }
+}
+
+impl<T> Default for JoinSet<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> Drop for JoinSet<T> {
+    fn drop(&mut self) {
+        self.abort_all();
+    }
+}
+
+impl<T> fmt::Debug for JoinSet<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("JoinSet").field("len", &self.len()).finish()
+    }
+}
@@ -0,0 +1,25 @@
+//! Utilities for the asynchronous tasks
+
+pub(crate) mod coop;
+pub(crate) mod scope;
+pub(crate) mod task_local;
+
+mod block_in_place;
+mod builder;
+mod id;
+mod join_set;
+mod priority;
+mod unconstrained;
+mod yield_now;
+
+pub use {
+    block_in_place::block_in_place,
+    builder::Builder,
+    id::{id, try_id, Id},
+    join_set::JoinSet,
+    priority::Priority,
+    scope::Scope,
+    task_local::{AccessError, LocalKey, TaskLocalFuture},
+    unconstrained::{unconstrained, Unconstrained},
+    yield_now::yield_now,
+};
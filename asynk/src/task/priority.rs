@@ -0,0 +1,24 @@
+/// Scheduling class of a task spawned with [`super::Builder::priority`].
+/// Runnable tasks of higher classes are polled first, while a class which keeps
+/// being passed over gets a turn after a while, see
+/// [`crate::AsynkBuilder::priority_aging`].
+///
+/// Tasks of local sets and synchronous jobs ignore the priority.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Priority {
+    /// Latency-sensitive tasks, e.g. health checks
+    High,
+    #[default]
+    Normal,
+    /// Bulk work which may wait for the other tasks
+    Background,
+}
+
+impl Priority {
+    /// All classes from the highest one
+    pub(crate) const ALL: [Self; 3] = [Self::High, Self::Normal, Self::Background];
+
+    pub(crate) fn index(self) -> usize {
+        self as usize
+    }
+}
@@ -0,0 +1,155 @@
+use crate::{executor::Executor, task::Priority, BlockOnError, JoinError, JoinHandle};
+use futures::FutureExt;
+use parking_lot::{Condvar, Mutex};
+use std::{
+    future::{poll_fn, Future},
+    marker::PhantomData,
+    mem,
+    panic::AssertUnwindSafe,
+    pin::Pin,
+    sync::Arc,
+    task::{Poll, Waker},
+};
+
+/// Scope of tasks which may borrow data living for `'env`, see
+/// [`crate::scope`]
+#[derive(Clone)]
+pub struct Scope<'env> {
+    exec: Arc<Executor>,
+    shared: Arc<Shared>,
+    /// Invariant over `'env`, like `std::thread::Scope`
+    _env: PhantomData<&'env mut &'env ()>,
+}
+
+struct Shared {
+    state: Mutex<State>,
+    /// Notified when the futures of all tasks are dropped
+    done: Condvar,
+}
+
+struct State {
+    /// Number of spawned tasks whose futures are not dropped yet
+    pending: usize,
+    /// Set when the scope is finished, so no more tasks can be spawned
+    closed: bool,
+    /// Waker of the scope waiting for the tasks
+    waker: Option<Waker>,
+}
+
+/// Decrements the number of pending tasks when the task future is dropped
+struct ChildGuard(Arc<Shared>);
+
+/// Future of a scoped task
+type ScopedFuture<'a, T> = Pin<Box<dyn Future<Output = T> + Send + 'a>>;
+
+impl<'env> Scope<'env> {
+    /// Spawn the task on the runtime of the scope. The task may borrow data
+    /// which outlives the scope.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the scope is already finished, e.g. if the scope was moved
+    /// into a task which outlived it
+    #[track_caller]
+    pub fn spawn<T>(&self, fut: impl Future<Output = T> + Send + 'env) -> JoinHandle<T>
+    where
+        T: Send + 'static,
+    {
+        {
+            let mut state = self.shared.state.lock();
+            assert!(!state.closed, "task is spawned on a finished scope");
+            state.pending += 1;
+        }
+
+        let guard = ChildGuard(Arc::clone(&self.shared));
+
+        let fut: ScopedFuture<'env, T> = Box::pin(async move {
+            // The guard is dropped together with the future
+            let _guard = guard;
+            fut.await
+        });
+
+        // SAFETY: `scope` doesn't return until the futures of all its tasks are
+        // dropped, even if the runtime stops polling them, and no tasks can be
+        // spawned after that, so the borrowed data outlives the future
+        let fut = unsafe { mem::transmute::<ScopedFuture<'env, T>, ScopedFuture<'static, T>>(fut) };
+
+        self.exec.spawn(fut, None, Priority::Normal)
+    }
+}
+
+impl Drop for ChildGuard {
+    fn drop(&mut self) {
+        let waker = {
+            let mut state = self.0.state.lock();
+            state.pending -= 1;
+
+            if state.pending == 0 {
+                self.0.done.notify_all();
+                state.waker.take()
+            } else {
+                None
+            }
+        };
+
+        if let Some(waker) = waker {
+            waker.wake();
+        }
+    }
+}
+
+/// Run the scope on the executor, blocking the current thread until the future
+/// returned by `f` and all tasks spawned on the scope are finished
+pub(crate) fn scope<'env, F, Fut>(exec: &Arc<Executor>, f: F) -> Result<Fut::Output, BlockOnError>
+where
+    F: FnOnce(Scope<'env>) -> Fut,
+    Fut: Future,
+{
+    let shared = Arc::new(Shared {
+        state: Mutex::new(State {
+            pending: 0,
+            closed: false,
+            waker: None,
+        }),
+        done: Condvar::new(),
+    });
+
+    let scope = Scope {
+        exec: Arc::clone(exec),
+        shared: Arc::clone(&shared),
+        _env: PhantomData,
+    };
+
+    let res = exec.block_on(async {
+        // The tasks must be waited for even if `f` or its future panics
+        let res = AssertUnwindSafe(async move { f(scope).await })
+            .catch_unwind()
+            .await;
+
+        poll_fn(|cx| {
+            let mut state = shared.state.lock();
+
+            if state.pending == 0 {
+                state.closed = true;
+                return Poll::Ready(());
+            }
+
+            state.waker = Some(cx.waker().clone());
+            Poll::Pending
+        })
+        .await;
+
+        res.map_err(|payload| JoinError::Panic(payload).into())
+    });
+
+    // `block_on` may return before the tasks are finished, e.g. when the runtime
+    // is shut down. Their futures are dropped by the runtime then.
+    let mut state = shared.state.lock();
+    state.closed = true;
+
+    while state.pending > 0 {
+        shared.done.wait(&mut state);
+    }
+
+    res?
+}
@@ -0,0 +1,310 @@
+use std::{
+    cell::RefCell,
+    fmt,
+    future::Future,
+    mem,
+    pin::Pin,
+    ptr,
+    task::{Context, Poll},
+    thread,
+};
+
+thread_local! {
+    /// Inheritable keys which are set on the current thread
+    static ACTIVE: RefCell<Vec<&'static dyn Snapshot>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Declare task-local keys. A value of the key is set for a future with
+/// [`LocalKey::scope`] and is visible to the code polled inside of it, no
+/// matter which worker thread polls the task.
+///
+/// Keys marked with `#[inherit]` (it must be the first attribute) are cloned
+/// into the tasks spawned with [`crate::spawn`] and [`crate::spawn_local`]
+/// while the value is set. Their type must be `Clone` and `Send`.
+///
+/// ```
+/// asynk::task_local! {
+///     static REQUEST_ID: u64;
+///
+///     #[inherit]
+///     pub static TENANT: String;
+/// }
+/// ```
+#[macro_export]
+macro_rules! task_local {
+    () => {};
+
+    (#[inherit] $(#[$attr:meta])* $vis:vis static $name:ident: $t:ty; $($rest:tt)*) => {
+        $crate::__task_local_inner!($(#[$attr])* $vis $name, $t, inheritable);
+        $crate::task_local!($($rest)*);
+    };
+
+    ($(#[$attr:meta])* $vis:vis static $name:ident: $t:ty; $($rest:tt)*) => {
+        $crate::__task_local_inner!($(#[$attr])* $vis $name, $t, new);
+        $crate::task_local!($($rest)*);
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __task_local_inner {
+    ($(#[$attr:meta])* $vis:vis $name:ident, $t:ty, $ctor:ident) => {
+        $(#[$attr])*
+        $vis static $name: $crate::task::LocalKey<$t> = {
+            ::std::thread_local! {
+                static __KEY: ::std::cell::RefCell<::std::option::Option<$t>> =
+                    const { ::std::cell::RefCell::new(::std::option::Option::None) };
+            }
+
+            $crate::task::LocalKey::$ctor(__KEY)
+        };
+    };
+}
+
+/// Key of a task-local value, declared with [`crate::task_local!`]
+pub struct LocalKey<T: 'static> {
+    /// Value of the future being polled on the current thread
+    inner: thread::LocalKey<RefCell<Option<T>>>,
+    /// Set if the key is inheritable
+    inherit: Option<SnapshotFn<T>>,
+}
+
+/// Clone the value of the key for a spawned task
+type SnapshotFn<T> = fn(&'static LocalKey<T>) -> Option<Box<dyn Inherited>>;
+
+/// Error returned when the task-local value is accessed outside of its scope
+#[derive(Debug, thiserror::Error)]
+#[error("task-local value is not set")]
+pub struct AccessError;
+
+/// Future which sets the task-local value while the inner future is polled
+pub struct TaskLocalFuture<T: 'static, F> {
+    key: &'static LocalKey<T>,
+    /// The value is moved into the key while the future is polled
+    value: Option<T>,
+    fut: F,
+}
+
+/// Task-local values inherited by a spawned task
+pub(crate) struct Inherit<F> {
+    values: Vec<Box<dyn Inherited>>,
+    fut: F,
+}
+
+/// Inheritable key which may be set on the current thread
+trait Snapshot {
+    /// Clone the current value of the key, if any
+    fn snapshot(&'static self) -> Option<Box<dyn Inherited>>;
+}
+
+/// Value of an inheritable key owned by a spawned task
+trait Inherited: Send {
+    /// Move the value into the key
+    fn enter(&mut self);
+    /// Move the value back from the key
+    fn exit(&mut self);
+}
+
+struct Value<T: 'static> {
+    key: &'static LocalKey<T>,
+    value: Option<T>,
+}
+
+impl<T: 'static> LocalKey<T> {
+    #[doc(hidden)]
+    pub const fn new(inner: thread::LocalKey<RefCell<Option<T>>>) -> Self {
+        Self {
+            inner,
+            inherit: None,
+        }
+    }
+
+    #[doc(hidden)]
+    pub const fn inheritable(inner: thread::LocalKey<RefCell<Option<T>>>) -> Self
+    where
+        T: Clone + Send,
+    {
+        Self {
+            inner,
+            inherit: Some(snapshot::<T>),
+        }
+    }
+
+    /// Set the value of the key while the future is polled
+    pub fn scope<F>(&'static self, value: T, fut: F) -> TaskLocalFuture<T, F>
+    where
+        F: Future,
+    {
+        TaskLocalFuture {
+            key: self,
+            value: Some(value),
+            fut,
+        }
+    }
+
+    /// Set the value of the key while `f` is running
+    pub fn sync_scope<R>(&'static self, value: T, f: impl FnOnce() -> R) -> R {
+        let mut value = Some(value);
+        let _guard = self.enter(&mut value);
+        f()
+    }
+
+    /// Call `f` with the current value of the key
+    ///
+    /// # Panics
+    ///
+    /// Panics if the value is not set
+    pub fn with<R>(&'static self, f: impl FnOnce(&T) -> R) -> R {
+        self.try_with(f)
+            .expect("task-local value must be set with `LocalKey::scope`")
+    }
+
+    /// Call `f` with the current value of the key, if it's set
+    pub fn try_with<R>(&'static self, f: impl FnOnce(&T) -> R) -> Result<R, AccessError> {
+        self.inner
+            .with_borrow(|value| value.as_ref().map(f).ok_or(AccessError))
+    }
+
+    /// Clone of the current value of the key
+    ///
+    /// # Panics
+    ///
+    /// Panics if the value is not set
+    pub fn get(&'static self) -> T
+    where
+        T: Clone,
+    {
+        self.with(T::clone)
+    }
+
+    /// Move the value into the key until the guard is dropped
+    fn enter<'a>(&'static self, value: &'a mut Option<T>) -> Guard<'a, T> {
+        self.swap(value);
+
+        if self.inherit.is_some() {
+            ACTIVE.with_borrow_mut(|active| active.push(self));
+        }
+
+        Guard { key: self, value }
+    }
+
+    fn exit(&'static self, value: &mut Option<T>) {
+        if self.inherit.is_some() {
+            ACTIVE.with_borrow_mut(|active| active.pop());
+        }
+
+        self.swap(value);
+    }
+
+    fn swap(&'static self, value: &mut Option<T>) {
+        self.inner
+            .with_borrow_mut(|current| mem::swap(current, value));
+    }
+}
+
+impl<T: 'static> fmt::Debug for LocalKey<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LocalKey").finish_non_exhaustive()
+    }
+}
+
+impl<T: 'static> Snapshot for LocalKey<T> {
+    fn snapshot(&'static self) -> Option<Box<dyn Inherited>> {
+        self.inherit.and_then(|snapshot| snapshot(self))
+    }
+}
+
+fn snapshot<T>(key: &'static LocalKey<T>) -> Option<Box<dyn Inherited>>
+where
+    T: Clone + Send + 'static,
+{
+    let value = key.inner.with_borrow(Option::clone)?;
+
+    Some(Box::new(Value {
+        key,
+        value: Some(value),
+    }))
+}
+
+impl<T: Send + 'static> Inherited for Value<T> {
+    fn enter(&mut self) {
+        // The guard isn't kept, the value is moved back by `exit`
+        mem::forget(self.key.enter(&mut self.value));
+    }
+
+    fn exit(&mut self) {
+        self.key.exit(&mut self.value);
+    }
+}
+
+/// Moves the value back from the key on drop
+struct Guard<'a, T: 'static> {
+    key: &'static LocalKey<T>,
+    value: &'a mut Option<T>,
+}
+
+impl<T: 'static> Drop for Guard<'_, T> {
+    fn drop(&mut self) {
+        self.key.exit(self.value);
+    }
+}
+
+impl<T, F> Future for TaskLocalFuture<T, F>
+where
+    F: Future,
+{
+    type Output = F::Output;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        // SAFETY: the inner future is never moved out of the pinned wrapper
+        let this = unsafe { self.get_unchecked_mut() };
+        let _guard = this.key.enter(&mut this.value);
+
+        // SAFETY: see above
+        unsafe { Pin::new_unchecked(&mut this.fut) }.poll(cx)
+    }
+}
+
+impl<F> Inherit<F> {
+    /// Capture the inheritable values which are set on the current thread
+    pub(crate) fn new(fut: F) -> Self {
+        let values = ACTIVE.with_borrow(|active| {
+            active
+                .iter()
+                .enumerate()
+                // A key set by nested scopes is inherited once, with the inner value
+                .filter(|&(i, key)| !active[..i].iter().any(|k| ptr::addr_eq(*k, *key)))
+                .filter_map(|(_, key)| key.snapshot())
+                .collect::<Vec<_>>()
+        });
+
+        Self { values, fut }
+    }
+}
+
+impl<F> Future for Inherit<F>
+where
+    F: Future,
+{
+    type Output = F::Output;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        /// Moves the values back from the keys on drop, even if the future panics
+        struct Exit<'a>(&'a mut [Box<dyn Inherited>]);
+
+        impl Drop for Exit<'_> {
+            fn drop(&mut self) {
+                self.0.iter_mut().rev().for_each(|value| value.exit());
+            }
+        }
+
+        // SAFETY: the inner future is never moved out of the pinned wrapper
+        let this = unsafe { self.get_unchecked_mut() };
+
+        this.values.iter_mut().for_each(|value| value.enter());
+        let _exit = Exit(&mut this.values);
+
+        // SAFETY: see above
+        unsafe { Pin::new_unchecked(&mut this.fut) }.poll(cx)
+    }
+}
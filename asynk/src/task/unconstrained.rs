@@ -0,0 +1,33 @@
+use super::coop;
+use std::{
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+/// Future which is polled without the cooperative budget, see [`unconstrained`]
+pub struct Unconstrained<F> {
+    inner: F,
+}
+
+/// Opt the future out of the cooperative budget. I/O resources polled by the
+/// future never force it to yield, so it may starve other tasks of the worker.
+pub fn unconstrained<F>(inner: F) -> Unconstrained<F>
+where
+    F: Future,
+{
+    Unconstrained { inner }
+}
+
+impl<F> Future for Unconstrained<F>
+where
+    F: Future,
+{
+    type Output = F::Output;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        // SAFETY: the inner future is never moved out of the pinned wrapper
+        let inner = unsafe { self.map_unchecked_mut(|this| &mut this.inner) };
+        coop::unconstrained(|| inner.poll(cx))
+    }
+}
@@ -0,0 +1,47 @@
+use asynk::task::block_in_place;
+use std::{num::NonZeroUsize, sync::mpsc, time::Duration};
+
+#[test]
+fn other_tasks_progress_while_worker_blocks() {
+    let rt = asynk::builder()
+        .task_threads(NonZeroUsize::MIN)
+        .build()
+        .unwrap();
+
+    let (tx, rx) = mpsc::channel();
+
+    let res = rt.block_on(async {
+        // Blocks the only worker until the next task runs
+        let blocked =
+            asynk::spawn(async move { block_in_place(|| rx.recv_timeout(Duration::from_secs(5))) });
+
+        asynk::spawn(async move { tx.send(()).unwrap() });
+
+        blocked.await
+    });
+
+    assert!(res.unwrap().unwrap().is_ok());
+}
+
+#[test]
+fn block_on_inside_block_in_place() {
+    let rt = asynk::builder()
+        .task_threads(NonZeroUsize::MIN)
+        .build()
+        .unwrap();
+
+    let res = rt.block_on(async {
+        asynk::spawn(async { block_in_place(|| asynk::block_on(asynk::spawn(async { 42 }))) }).await
+    });
+
+    assert_eq!(res.unwrap().unwrap().unwrap().unwrap(), 42);
+}
+
+#[test]
+fn block_in_place_on_current_thread_runs_in_place() {
+    let rt = asynk::builder().current_thread().build().unwrap();
+
+    let res = rt.block_on(async { block_in_place(|| 42) });
+
+    assert_eq!(res.unwrap(), 42);
+}
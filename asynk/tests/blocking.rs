@@ -0,0 +1,143 @@
+use asynk::JoinError;
+use std::{
+    num::NonZeroUsize,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc, Arc,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+/// Runtime with a single blocking thread and a queue of a single job
+fn runtime() -> asynk::Runtime {
+    asynk::builder()
+        .blocking_threads(NonZeroUsize::MIN)
+        .max_blocking_queue(1)
+        .build()
+        .unwrap()
+}
+
+#[test]
+fn job_takes_ownership() {
+    let rt = runtime();
+    let data = vec![1, 2, 3];
+
+    let sum = rt.block_on(rt.spawn_blocking(move || data.into_iter().sum::<i32>()));
+
+    assert_eq!(sum.unwrap().unwrap(), 6);
+}
+
+#[test]
+fn full_queue_rejects_jobs() {
+    let rt = runtime();
+    let (tx, rx) = mpsc::channel::<()>();
+
+    // Occupies the only thread
+    let busy = rt.spawn_blocking(move || rx.recv().unwrap());
+    let queued = rt.spawn_blocking(|| 42);
+
+    assert!(rt.try_spawn_blocking(|| 0).is_err());
+    let rejected = rt.spawn_blocking(|| 0);
+
+    tx.send(()).unwrap();
+
+    rt.block_on(async {
+        assert!(matches!(rejected.await, Err(JoinError::QueueFull)));
+        busy.await.unwrap();
+        assert_eq!(queued.await.unwrap(), 42);
+    })
+    .unwrap();
+}
+
+#[test]
+fn aborted_queued_job_is_not_run() {
+    let rt = runtime();
+    let (tx, rx) = mpsc::channel::<()>();
+    let ran = Arc::new(AtomicBool::new(false));
+
+    let busy = rt.spawn_blocking(move || rx.recv().unwrap());
+
+    let queued = {
+        let ran = Arc::clone(&ran);
+        rt.spawn_blocking(move || ran.store(true, Ordering::SeqCst))
+    };
+
+    queued.abort();
+    tx.send(()).unwrap();
+
+    rt.block_on(async {
+        busy.await.unwrap();
+        assert!(matches!(queued.await, Err(JoinError::Cancelled)));
+    })
+    .unwrap();
+
+    assert!(!ran.load(Ordering::SeqCst));
+}
+
+/// Name of the thread running the blocking job
+fn job_thread(rt: &asynk::Runtime) -> String {
+    let name = rt.spawn_blocking(|| thread::current().name().map(str::to_owned));
+    rt.block_on(name).unwrap().unwrap().unwrap()
+}
+
+#[test]
+fn idle_thread_is_retired_after_keep_alive() {
+    let rt = asynk::builder()
+        .thread_keep_alive(Duration::from_millis(200))
+        .build()
+        .unwrap();
+
+    // The idle thread is reused until it's retired
+    assert_eq!(job_thread(&rt), "asynk-blocking-0");
+    assert_eq!(job_thread(&rt), "asynk-blocking-0");
+    assert_eq!(rt.metrics().blocking.threads, 1);
+
+    let deadline = Instant::now() + Duration::from_secs(5);
+
+    while rt.metrics().blocking.threads > 0 {
+        assert!(Instant::now() < deadline, "idle thread is not retired");
+        thread::sleep(Duration::from_millis(10));
+    }
+
+    // A new thread is started for the next job
+    assert_eq!(job_thread(&rt), "asynk-blocking-1");
+}
+
+#[test]
+fn compute_job_runs_on_compute_thread() {
+    let rt = asynk::builder()
+        .compute_threads(NonZeroUsize::MIN)
+        .build()
+        .unwrap();
+
+    let name = rt.spawn_compute(|| thread::current().name().map(str::to_owned));
+
+    assert_eq!(
+        rt.block_on(name).unwrap().unwrap().as_deref(),
+        Some("asynk-compute-0")
+    );
+    assert_eq!(rt.metrics().compute.threads, 1);
+    assert_eq!(rt.metrics().blocking.threads, 0);
+}
+
+#[test]
+fn compute_job_does_not_wait_for_blocking_jobs() {
+    let rt = runtime();
+    let (tx, rx) = mpsc::channel::<()>();
+
+    // The blocking pool is saturated, so the job would be queued there
+    let busy = rt.spawn_blocking(move || rx.recv().unwrap());
+    let queued = rt.spawn_blocking(|| ());
+
+    let res = rt.block_on(rt.spawn_compute(|| 42));
+    assert_eq!(res.unwrap().unwrap(), 42);
+
+    tx.send(()).unwrap();
+
+    rt.block_on(async {
+        busy.await.unwrap();
+        queued.await.unwrap();
+    })
+    .unwrap();
+}
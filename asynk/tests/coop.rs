@@ -0,0 +1,60 @@
+use asynk::net::UdpSocket;
+use std::{
+    net::SocketAddr,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+};
+
+const SENDS: usize = 1000;
+
+/// Send datagrams, which never waits for the socket, until the flag is set.
+/// Returns `true` if the flag was set before all datagrams were sent.
+async fn send_until_flag(flag: Arc<AtomicBool>, target: SocketAddr) -> bool {
+    let socket = UdpSocket::bind("127.0.0.1:0".parse().unwrap()).unwrap();
+
+    for _ in 0..SENDS {
+        if flag.load(Ordering::SeqCst) {
+            return true;
+        }
+
+        socket.send_to(b"x", target).await.unwrap();
+    }
+
+    false
+}
+
+/// Run the sending task and the task setting the flag on a current-thread
+/// runtime, returning the result of the sending task
+fn run(unconstrained: bool) -> bool {
+    let rt = asynk::builder().current_thread().build().unwrap();
+    let receiver = std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+    let target = receiver.local_addr().unwrap();
+    let flag = Arc::new(AtomicBool::new(false));
+
+    let sender = {
+        let flag = Arc::clone(&flag);
+
+        if unconstrained {
+            rt.spawn(asynk::task::unconstrained(send_until_flag(flag, target)))
+        } else {
+            rt.spawn(send_until_flag(flag, target))
+        }
+    };
+
+    // Spawned after the sender, so it's polled only if the sender yields
+    rt.spawn(async move { flag.store(true, Ordering::SeqCst) });
+
+    rt.block_on(sender).unwrap().unwrap()
+}
+
+#[test]
+fn budget_forces_yield() {
+    assert!(run(false));
+}
+
+#[test]
+fn unconstrained_task_is_not_forced_to_yield() {
+    assert!(!run(true));
+}
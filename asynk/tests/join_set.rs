@@ -0,0 +1,77 @@
+use asynk::JoinSet;
+use futures::{channel::oneshot, future};
+
+#[test]
+fn join_next_returns_tasks_in_completion_order() {
+    let rt = asynk::builder().build().unwrap();
+
+    let res = rt.block_on(async {
+        let mut set = JoinSet::new();
+
+        let senders = (0..3)
+            .map(|i| {
+                let (tx, rx) = oneshot::channel::<()>();
+                set.spawn(async move {
+                    rx.await.unwrap();
+                    i
+                });
+                tx
+            })
+            .collect::<Vec<_>>();
+
+        assert_eq!(set.len(), 3);
+        assert!(set.try_join_next().is_none());
+
+        let mut order = Vec::new();
+
+        for (i, tx) in senders.into_iter().enumerate().rev() {
+            tx.send(()).unwrap();
+            order.push((i, set.join_next().await.unwrap().unwrap()));
+        }
+
+        assert!(set.is_empty());
+        assert!(set.join_next().await.is_none());
+        order
+    });
+
+    assert_eq!(res.unwrap(), [(2, 2), (1, 1), (0, 0)]);
+}
+
+#[test]
+fn drop_aborts_tasks() {
+    let rt = asynk::builder().build().unwrap();
+
+    let mut set = JoinSet::new();
+    let abort = set.spawn_on(future::pending::<()>(), rt.handle());
+    let jh = rt.spawn(future::pending::<()>());
+    let inserted = set.insert(jh);
+
+    drop(set);
+
+    // The tasks are finished once their futures are dropped
+    rt.block_on(async {
+        while !abort.is_finished() || !inserted.is_finished() {
+            asynk::task::yield_now().await;
+        }
+    })
+    .unwrap();
+}
+
+#[test]
+fn shutdown_aborts_and_joins_tasks() {
+    let rt = asynk::builder().build().unwrap();
+
+    rt.block_on(async {
+        let mut set = JoinSet::new();
+        set.spawn(future::pending::<()>());
+        set.spawn(future::pending::<()>());
+
+        set.shutdown().await;
+        assert!(set.is_empty());
+
+        set.spawn(future::pending::<()>());
+        set.abort_all();
+        assert!(set.join_next().await.unwrap().unwrap_err().is_cancelled());
+    })
+    .unwrap();
+}
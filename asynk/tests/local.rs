@@ -0,0 +1,92 @@
+use asynk::LocalSet;
+use futures::future;
+use std::{
+    cell::{Cell, RefCell},
+    rc::Rc,
+    thread,
+};
+
+#[test]
+fn local_tasks_hold_rc_across_awaits() {
+    let rt = asynk::builder().build().unwrap();
+    let local = LocalSet::new();
+    let values = Rc::new(RefCell::new(Vec::new()));
+
+    let handles = (0..3)
+        .map(|i| {
+            let values = Rc::clone(&values);
+
+            local.spawn_local(async move {
+                asynk::task::yield_now().await;
+                values.borrow_mut().push(i);
+                thread::current().id()
+            })
+        })
+        .collect::<Vec<_>>();
+
+    let threads = local.block_on(&rt, future::join_all(handles)).unwrap();
+
+    let mut values = values.borrow().clone();
+    values.sort();
+    assert_eq!(values, [0, 1, 2]);
+
+    // Local tasks are polled on the thread which drives the set
+    for id in threads {
+        assert_eq!(id.unwrap(), thread::current().id());
+    }
+}
+
+#[test]
+fn spawn_local_in_run_until() {
+    let rt = asynk::builder().current_thread().build().unwrap();
+    let local = LocalSet::new();
+
+    let res = rt.block_on(local.run_until(async {
+        let counter = Rc::new(Cell::new(0));
+
+        let jh = asynk::spawn_local({
+            let counter = Rc::clone(&counter);
+
+            async move {
+                // Local tasks can await the tasks of the runtime
+                let add = asynk::spawn(async { 2 }).await.unwrap();
+                counter.set(counter.get() + add);
+            }
+        });
+
+        jh.await.unwrap();
+        counter.get()
+    }));
+
+    assert_eq!(res.unwrap(), 2);
+}
+
+#[test]
+fn drop_cancels_local_tasks() {
+    struct DropFlag(Rc<Cell<bool>>);
+
+    impl Drop for DropFlag {
+        fn drop(&mut self) {
+            self.0.set(true);
+        }
+    }
+
+    let local = LocalSet::new();
+    let dropped = Rc::new(Cell::new(false));
+
+    let flag = DropFlag(Rc::clone(&dropped));
+    let jh = local.spawn_local(async move {
+        let _flag = flag;
+        future::pending::<()>().await
+    });
+
+    drop(local);
+    assert!(dropped.get());
+    assert!(futures::executor::block_on(jh).unwrap_err().is_shutdown());
+}
+
+#[test]
+#[should_panic(expected = "local set")]
+fn spawn_local_outside_local_set() {
+    asynk::spawn_local(async {});
+}
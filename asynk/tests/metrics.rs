@@ -0,0 +1,112 @@
+use asynk::net::TcpListener;
+use futures::StreamExt;
+use std::{net::TcpStream, num::NonZeroUsize, sync::mpsc, thread};
+
+#[test]
+fn task_counters() {
+    let rt = asynk::builder().current_thread().build().unwrap();
+
+    // Nothing polls the tasks until the runtime is blocked on
+    let handles = (0..3).map(|_| rt.spawn(async {})).collect::<Vec<_>>();
+
+    let metrics = rt.metrics();
+    assert_eq!(metrics.workers, 0);
+    assert_eq!(metrics.alive_tasks, 3);
+    assert_eq!(metrics.spawned_tasks, 3);
+    assert_eq!(metrics.completed_tasks, 0);
+    assert_eq!(metrics.global_queue_depth, 3);
+
+    rt.block_on(async {
+        for jh in handles {
+            jh.await.unwrap();
+        }
+    })
+    .unwrap();
+
+    let metrics = rt.metrics();
+    assert_eq!(metrics.alive_tasks, 0);
+    assert_eq!(metrics.spawned_tasks, 3);
+    assert_eq!(metrics.completed_tasks, 3);
+    assert_eq!(metrics.polls, 3);
+    assert_eq!(metrics.global_queue_depth, 0);
+}
+
+#[test]
+fn worker_gauges() {
+    let rt = asynk::builder()
+        .task_threads(NonZeroUsize::new(2).unwrap())
+        .build()
+        .unwrap();
+
+    let metrics = rt.metrics();
+    assert_eq!(metrics.workers, 2);
+    assert_eq!(metrics.worker_queue_depths, [0, 0]);
+}
+
+#[test]
+fn blocking_pool_gauges() {
+    let rt = asynk::builder()
+        .blocking_threads(NonZeroUsize::MIN)
+        .build()
+        .unwrap();
+
+    let (started_tx, started_rx) = mpsc::channel();
+    let (tx, rx) = mpsc::channel::<()>();
+
+    let busy = rt.spawn_blocking(move || {
+        started_tx.send(()).unwrap();
+        rx.recv().unwrap();
+    });
+
+    started_rx.recv().unwrap();
+    let queued = rt.spawn_blocking(|| ());
+
+    let metrics = rt.metrics().blocking;
+    assert_eq!(metrics.threads, 1);
+    assert_eq!(metrics.busy_threads, 1);
+    assert_eq!(metrics.queue_depth, 1);
+
+    tx.send(()).unwrap();
+
+    rt.block_on(async {
+        busy.await.unwrap();
+        queued.await.unwrap();
+    })
+    .unwrap();
+
+    assert_eq!(rt.metrics().blocking.queue_depth, 0);
+}
+
+#[test]
+fn io_counters() {
+    let (parked_tx, parked_rx) = mpsc::channel();
+
+    let rt = asynk::builder()
+        .current_thread()
+        .on_thread_park(move || _ = parked_tx.send(()))
+        .build()
+        .unwrap();
+
+    rt.block_on(async {
+        let listener = TcpListener::bind("127.0.0.1:0".parse().unwrap()).unwrap();
+        let addr = listener.local_addr().unwrap();
+        let mut accept = listener.accept().unwrap();
+
+        assert_eq!(rt.metrics().io_sources, 1);
+
+        // The connection arrives when the thread waits for the events
+        let client = thread::spawn(move || {
+            parked_rx.recv().unwrap();
+            TcpStream::connect(addr).unwrap()
+        });
+
+        let (stream, _) = accept.next().await.unwrap().unwrap();
+        assert!(rt.metrics().io_events > 0);
+
+        drop((accept, stream));
+        assert_eq!(rt.metrics().io_sources, 0);
+
+        client.join().unwrap();
+    })
+    .unwrap();
+}
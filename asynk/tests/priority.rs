@@ -0,0 +1,108 @@
+use asynk::{
+    task::{Builder, Priority},
+    JoinHandle, Runtime,
+};
+use parking_lot::Mutex;
+use std::{
+    num::{NonZeroU32, NonZeroUsize},
+    sync::Arc,
+};
+
+type Order = Arc<Mutex<Vec<Priority>>>;
+
+/// Spawn the task recording its priority when it's polled
+fn spawn(rt: &Runtime, order: &Order, priority: Priority) -> JoinHandle<()> {
+    let order = Arc::clone(order);
+
+    Builder::new()
+        .priority(priority)
+        .spawn_on(async move { order.lock().push(priority) }, rt.handle())
+}
+
+/// Wait for the tasks, which are polled by the runtime in the meantime
+fn join_all(rt: &Runtime, handles: Vec<JoinHandle<()>>) {
+    rt.block_on(async {
+        for jh in handles {
+            jh.await.unwrap();
+        }
+    })
+    .unwrap();
+}
+
+#[test]
+fn higher_priority_is_polled_first() {
+    let rt = asynk::builder().current_thread().build().unwrap();
+    let order = Order::default();
+
+    let handles = [Priority::Background, Priority::Normal, Priority::High]
+        .map(|priority| spawn(&rt, &order, priority))
+        .into();
+
+    join_all(&rt, handles);
+
+    assert_eq!(
+        *order.lock(),
+        [Priority::High, Priority::Normal, Priority::Background]
+    );
+}
+
+#[test]
+fn starving_priority_gets_a_turn() {
+    let rt = asynk::builder()
+        .current_thread()
+        .priority_aging(NonZeroU32::new(2).unwrap())
+        .build()
+        .unwrap();
+
+    let order = Order::default();
+    let mut handles = vec![spawn(&rt, &order, Priority::Background)];
+    handles.extend((0..4).map(|_| spawn(&rt, &order, Priority::High)));
+
+    join_all(&rt, handles);
+
+    // The background task is passed over by two high ones at most
+    assert_eq!(
+        *order.lock(),
+        [
+            Priority::High,
+            Priority::High,
+            Priority::Background,
+            Priority::High,
+            Priority::High,
+        ]
+    );
+}
+
+#[test]
+fn worker_polls_higher_priority_first() {
+    let rt = asynk::builder()
+        .task_threads(NonZeroUsize::MIN)
+        .build()
+        .unwrap();
+
+    let order = Order::default();
+
+    // The tasks are spawned by a task, so the only worker polls them after it
+    let jh = rt.spawn({
+        let rt = rt.handle().clone();
+        let order = Arc::clone(&order);
+
+        async move {
+            [Priority::Background, Priority::Normal, Priority::High].map(|priority| {
+                let order = Arc::clone(&order);
+
+                Builder::new()
+                    .priority(priority)
+                    .spawn_on(async move { order.lock().push(priority) }, &rt)
+            })
+        }
+    });
+
+    let handles = rt.block_on(jh).unwrap().unwrap().into();
+    join_all(&rt, handles);
+
+    assert_eq!(
+        *order.lock(),
+        [Priority::High, Priority::Normal, Priority::Background]
+    );
+}
@@ -0,0 +1,235 @@
+use futures::future;
+use std::{
+    cell::Cell,
+    num::NonZeroUsize,
+    rc::Rc,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+#[test]
+fn block_on_in_runtime_context() {
+    let rt = asynk::builder().build().unwrap();
+    let _enter = rt.enter();
+
+    // The future is neither `Send` nor `'static`
+    let local = Rc::new(Cell::new(1));
+    let res = asynk::block_on(async {
+        local.set(local.get() + asynk::spawn(async { 1 }).await.unwrap());
+        local.get()
+    });
+
+    assert_eq!(res.unwrap(), 2);
+}
+
+#[test]
+fn runtimes_are_independent() {
+    let first = asynk::builder().build().unwrap();
+    let second = asynk::builder().current_thread().build().unwrap();
+
+    let id = first.block_on(async { asynk::spawn(async { asynk::task::id() }).await });
+    drop(first);
+
+    assert!(id.unwrap().is_ok());
+    assert_eq!(
+        second
+            .block_on(second.spawn(async { 42 }))
+            .unwrap()
+            .unwrap(),
+        42
+    );
+}
+
+#[test]
+fn shutdown_cancels_tasks() {
+    let rt = asynk::builder().build().unwrap();
+
+    let jh = rt.spawn(future::pending::<()>());
+    rt.shutdown_timeout(Duration::from_millis(100));
+
+    let res = futures::executor::block_on(jh);
+    assert!(res.unwrap_err().is_shutdown());
+}
+
+#[test]
+fn shutdown_rejects_new_tasks() {
+    let rt = asynk::builder().build().unwrap();
+    let handle = rt.handle().clone();
+    drop(rt);
+
+    let res = futures::executor::block_on(handle.spawn(async { 42 }));
+    assert!(res.unwrap_err().is_shutdown());
+}
+
+#[test]
+fn shutdown_timeout_waits_for_completing_tasks() {
+    let rt = asynk::builder().build().unwrap();
+
+    let jh = rt.spawn(async {
+        futures_timer::Delay::new(Duration::from_millis(20)).await;
+        42
+    });
+    rt.shutdown_timeout(Duration::from_secs(10));
+
+    assert_eq!(futures::executor::block_on(jh).unwrap(), 42);
+}
+
+#[test]
+fn shutdown_timeout_joins_idle_threads() {
+    let stopped = Arc::new(AtomicUsize::new(0));
+
+    let rt = asynk::builder()
+        .task_threads(NonZeroUsize::new(4).unwrap())
+        .on_thread_stop({
+            let stopped = Arc::clone(&stopped);
+            move || {
+                // Detached threads would be still stopping
+                thread::sleep(Duration::from_millis(20));
+                stopped.fetch_add(1, Ordering::SeqCst);
+            }
+        })
+        .build()
+        .unwrap();
+
+    // Uses up the grace period
+    let _jh = rt.spawn(future::pending::<()>());
+    rt.shutdown_timeout(Duration::from_millis(100));
+
+    assert_eq!(stopped.load(Ordering::SeqCst), 4);
+}
+
+#[test]
+fn block_on_fails_on_shutdown() {
+    for current_thread in [false, true] {
+        let builder = asynk::builder();
+        let builder = if current_thread {
+            builder.current_thread()
+        } else {
+            builder
+        };
+
+        let rt = builder.build().unwrap();
+        let handle = rt.handle().clone();
+
+        let blocked = thread::spawn(move || handle.block_on(future::pending::<()>()));
+        drop(rt);
+
+        let err = blocked.join().unwrap().unwrap_err();
+        assert!(matches!(err, asynk::BlockOnError::Shutdown));
+    }
+}
+
+#[test]
+fn shutdown_cancels_all_owned_tasks() {
+    let rt = asynk::builder().build().unwrap();
+
+    let handles = (0..1000)
+        .map(|_| rt.spawn(future::pending::<()>()))
+        .collect::<Vec<_>>();
+
+    let metrics = rt.metrics();
+    assert_eq!(metrics.alive_tasks, 1000);
+    assert_eq!(metrics.spawned_tasks, 1000);
+
+    let handle = rt.handle().clone();
+    drop(rt);
+
+    for jh in handles {
+        assert!(futures::executor::block_on(jh).unwrap_err().is_shutdown());
+    }
+
+    let metrics = handle.metrics();
+    assert_eq!(metrics.alive_tasks, 0);
+    assert_eq!(metrics.completed_tasks, 1000);
+}
+
+#[test]
+fn current_thread_polls_tasks_during_shutdown_grace() {
+    let rt = asynk::builder().current_thread().build().unwrap();
+
+    let ready = rt.spawn(async { 42 });
+    let delayed = rt.spawn(async {
+        futures_timer::Delay::new(Duration::from_millis(20)).await;
+        asynk::task::yield_now().await;
+        43
+    });
+
+    let start = Instant::now();
+    rt.shutdown_timeout(Duration::from_secs(10));
+    assert!(start.elapsed() < Duration::from_secs(5));
+
+    assert_eq!(futures::executor::block_on(ready).unwrap(), 42);
+    assert_eq!(futures::executor::block_on(delayed).unwrap(), 43);
+}
+
+#[test]
+fn current_thread_cancels_tasks_after_shutdown_grace() {
+    let rt = asynk::builder().current_thread().build().unwrap();
+
+    let jh = rt.spawn(future::pending::<()>());
+    rt.shutdown_timeout(Duration::from_millis(50));
+
+    assert!(futures::executor::block_on(jh).unwrap_err().is_shutdown());
+}
+
+#[test]
+fn current_thread_polls_tasks_on_block_on_thread() {
+    let rt = asynk::builder().current_thread().build().unwrap();
+    let current = thread::current().id();
+
+    let res = rt.block_on(async { asynk::spawn(async { thread::current().id() }).await });
+
+    assert_eq!(res.unwrap().unwrap(), current);
+}
+
+#[test]
+fn nested_block_on_is_reentrant() {
+    let rt = asynk::builder().build().unwrap();
+    let handle = rt.handle().clone();
+
+    let res = rt.block_on(async { handle.block_on(async {}) }).unwrap();
+    assert!(matches!(res, Err(asynk::BlockOnError::Reentrant)));
+
+    let res = rt
+        .block_on(rt.spawn(async {
+            asynk::Handle::current()
+                .block_on(async {})
+                .is_err_and(|e| matches!(e, asynk::BlockOnError::Reentrant))
+        }))
+        .unwrap();
+    assert!(res.unwrap());
+}
+
+#[test]
+fn concurrent_block_on_share_current_thread() {
+    let rt = asynk::builder().current_thread().build().unwrap();
+
+    thread::scope(|s| {
+        let threads = (0..4)
+            .map(|i| {
+                let handle = rt.handle().clone();
+
+                s.spawn(move || {
+                    handle.block_on(async move {
+                        let mut sum = 0;
+
+                        for _ in 0..1000 {
+                            asynk::task::yield_now().await;
+                            sum += asynk::spawn(async move { i }).await.unwrap();
+                        }
+
+                        sum
+                    })
+                })
+            })
+            .collect::<Vec<_>>();
+
+        for (i, thread) in threads.into_iter().enumerate() {
+            assert_eq!(thread.join().unwrap().unwrap(), i * 1000);
+        }
+    });
+}
@@ -0,0 +1,89 @@
+use parking_lot::Mutex;
+use std::{
+    num::NonZeroUsize,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+};
+
+#[test]
+fn nested_spawns_complete_on_all_workers() {
+    let rt = asynk::builder()
+        .task_threads(NonZeroUsize::new(4).unwrap())
+        .build()
+        .unwrap();
+
+    let count = Arc::new(AtomicUsize::new(0));
+
+    rt.block_on({
+        let count = Arc::clone(&count);
+
+        async move {
+            let handles = (0..100)
+                .map(|_| {
+                    let count = Arc::clone(&count);
+
+                    asynk::spawn(async move {
+                        let handles = (0..100)
+                            .map(|_| {
+                                let count = Arc::clone(&count);
+
+                                asynk::spawn(async move {
+                                    asynk::task::yield_now().await;
+                                    count.fetch_add(1, Ordering::Relaxed);
+                                })
+                            })
+                            .collect::<Vec<_>>();
+
+                        for jh in handles {
+                            jh.await.unwrap();
+                        }
+                    })
+                })
+                .collect::<Vec<_>>();
+
+            for jh in handles {
+                jh.await.unwrap();
+            }
+        }
+    })
+    .unwrap();
+
+    assert_eq!(count.load(Ordering::Relaxed), 10_000);
+}
+
+#[test]
+fn lifo_slot_polls_last_woken_task_first() {
+    let rt = asynk::builder()
+        .task_threads(NonZeroUsize::MIN)
+        .build()
+        .unwrap();
+
+    let order = Arc::new(Mutex::new(Vec::new()));
+
+    let jh = rt.spawn({
+        let order = Arc::clone(&order);
+
+        async move {
+            let first = asynk::spawn({
+                let order = Arc::clone(&order);
+                async move { order.lock().push("first") }
+            });
+
+            let second = asynk::spawn({
+                let order = Arc::clone(&order);
+                async move { order.lock().push("second") }
+            });
+
+            first.await.unwrap();
+            second.await.unwrap();
+        }
+    });
+
+    rt.block_on(jh).unwrap().unwrap();
+
+    // The task spawned last is in the LIFO slot, the other one was moved to
+    // the local queue
+    assert_eq!(*order.lock(), ["second", "first"]);
+}
@@ -0,0 +1,91 @@
+use futures::future;
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc,
+    },
+    thread,
+    time::Duration,
+};
+
+#[test]
+fn scope_waits_for_borrowing_tasks() {
+    let rt = asynk::builder().build().unwrap();
+    let mut chunks = vec![vec![1, 2], vec![3, 4]];
+    let borrowed = &mut chunks;
+
+    rt.scope(|s| async move {
+        for chunk in borrowed {
+            s.spawn(async move {
+                asynk::task::yield_now().await;
+                chunk.iter_mut().for_each(|x| *x *= 2);
+            });
+        }
+    })
+    .unwrap();
+
+    assert_eq!(chunks, [[2, 4], [6, 8]]);
+}
+
+#[test]
+fn scope_returns_output_of_the_future() {
+    let rt = asynk::builder().current_thread().build().unwrap();
+    let value = 20;
+
+    let res = rt.scope(|s| async move { s.spawn(async move { value + 1 }).await.unwrap() * 2 });
+
+    assert_eq!(res.unwrap(), 42);
+}
+
+/// Sets the borrowed flag on drop, slowly enough for the scope to return
+/// first if it doesn't wait for the drop
+struct Guard<'a>(&'a AtomicBool);
+
+impl Drop for Guard<'_> {
+    fn drop(&mut self) {
+        thread::sleep(Duration::from_millis(50));
+        self.0.store(true, Ordering::SeqCst);
+    }
+}
+
+/// Shut the runtime down from another thread while a task of the scope holds
+/// a guard borrowing the stack frame
+fn shutdown_during_scope(rt: asynk::Runtime) {
+    let handle = rt.handle().clone();
+    let dropped = AtomicBool::new(false);
+    let (tx, rx) = mpsc::channel();
+
+    let shutdown = thread::spawn(move || {
+        rx.recv().unwrap();
+        rt.shutdown_background();
+    });
+
+    let res = handle.scope(|s| {
+        let dropped = &dropped;
+
+        async move {
+            s.spawn(async move {
+                let _guard = Guard(dropped);
+                tx.send(()).unwrap();
+                future::pending::<()>().await
+            });
+
+            future::pending::<()>().await
+        }
+    });
+
+    assert!(matches!(res, Err(asynk::BlockOnError::Shutdown)));
+    assert!(dropped.load(Ordering::SeqCst));
+
+    shutdown.join().unwrap();
+}
+
+#[test]
+fn scope_outlives_tasks_on_shutdown() {
+    shutdown_during_scope(asynk::builder().build().unwrap());
+}
+
+#[test]
+fn scope_outlives_tasks_on_current_thread_shutdown() {
+    shutdown_during_scope(asynk::builder().current_thread().build().unwrap());
+}
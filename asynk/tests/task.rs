@@ -0,0 +1,197 @@
+use futures::future;
+use std::{
+    num::NonZeroUsize,
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Arc,
+    },
+    task::Poll,
+};
+
+/// Sets the flag when dropped, to check that a task future is dropped
+struct DropFlag(Arc<AtomicBool>);
+
+impl Drop for DropFlag {
+    fn drop(&mut self) {
+        self.0.store(true, Ordering::SeqCst);
+    }
+}
+
+#[test]
+fn wake_while_running_repolls_once() {
+    let rt = asynk::builder().current_thread().build().unwrap();
+    let polls = Arc::new(AtomicUsize::new(0));
+
+    let jh = rt.spawn({
+        let polls = Arc::clone(&polls);
+
+        future::poll_fn(move |cx| match polls.fetch_add(1, Ordering::SeqCst) {
+            0 => {
+                cx.waker().wake_by_ref();
+                cx.waker().wake_by_ref();
+                cx.waker().wake_by_ref();
+                Poll::Pending
+            }
+            _ => Poll::Ready(()),
+        })
+    });
+
+    rt.block_on(jh).unwrap().unwrap();
+
+    // The block_on thread polls the tasks, so redundant entries would stay
+    // queued after the task is completed
+    let metrics = rt.metrics();
+    assert_eq!(polls.load(Ordering::SeqCst), 2);
+    assert_eq!(metrics.polls, 2);
+    assert_eq!(metrics.global_queue_depth, 0);
+}
+
+#[test]
+fn abort_cancels_task() {
+    let rt = asynk::builder().build().unwrap();
+    let dropped = Arc::new(AtomicBool::new(false));
+
+    let jh = rt.spawn({
+        let flag = DropFlag(Arc::clone(&dropped));
+
+        async move {
+            let _flag = flag;
+            future::pending::<()>().await
+        }
+    });
+
+    jh.abort();
+
+    let res = rt.block_on(jh).unwrap();
+    assert!(res.unwrap_err().is_cancelled());
+    assert!(dropped.load(Ordering::SeqCst));
+}
+
+#[test]
+fn abort_handle_cancels_task() {
+    let rt = asynk::builder().build().unwrap();
+
+    let jh = rt.spawn(future::pending::<()>());
+    let abort = jh.abort_handle();
+    assert_eq!(abort.id(), jh.id());
+
+    abort.abort();
+
+    let res = rt.block_on(jh).unwrap();
+    assert!(res.unwrap_err().is_cancelled());
+    assert!(abort.is_finished());
+}
+
+#[test]
+fn abort_after_completion_keeps_output() {
+    let rt = asynk::builder().build().unwrap();
+
+    let jh = rt.spawn(async { 42 });
+
+    while !jh.is_finished() {
+        std::thread::yield_now();
+    }
+
+    jh.abort();
+    assert_eq!(rt.block_on(jh).unwrap().unwrap(), 42);
+}
+
+#[test]
+fn panic_is_reported() {
+    let rt = asynk::builder().build().unwrap();
+
+    let res = rt.block_on(rt.spawn(async { panic!("boom") })).unwrap();
+
+    let payload = res.unwrap_err().try_into_panic().unwrap();
+    assert_eq!(payload.downcast_ref::<&str>(), Some(&"boom"));
+}
+
+#[test]
+fn panic_keeps_worker_alive() {
+    let rt = asynk::builder()
+        .task_threads(NonZeroUsize::MIN)
+        .build()
+        .unwrap();
+
+    for _ in 0..3 {
+        let res = rt.block_on(rt.spawn(async { panic!("boom") })).unwrap();
+        assert!(res.unwrap_err().is_panic());
+    }
+
+    assert_eq!(rt.block_on(rt.spawn(async { 42 })).unwrap().unwrap(), 42);
+}
+
+#[test]
+fn panic_in_drop_is_reported() {
+    struct PanicOnDrop;
+
+    impl Drop for PanicOnDrop {
+        fn drop(&mut self) {
+            panic!("boom in drop");
+        }
+    }
+
+    let rt = asynk::builder()
+        .task_threads(NonZeroUsize::MIN)
+        .build()
+        .unwrap();
+
+    let res = rt
+        .block_on(rt.spawn(async {
+            let _guard = PanicOnDrop;
+        }))
+        .unwrap();
+    assert!(res.unwrap_err().is_panic());
+
+    let guard = PanicOnDrop;
+    let jh = rt.spawn(async move {
+        let _guard = guard;
+        future::pending::<()>().await
+    });
+    jh.abort();
+
+    let res = rt.block_on(jh).unwrap();
+    assert!(res.unwrap_err().is_panic());
+
+    // The worker is still alive and the runtime can be dropped
+    assert_eq!(rt.block_on(rt.spawn(async { 42 })).unwrap().unwrap(), 42);
+}
+
+#[test]
+fn block_on_panic_is_reported() {
+    let rt = asynk::builder().build().unwrap();
+
+    let err = rt.block_on(async { panic!("boom") }).unwrap_err();
+    assert!(matches!(err, asynk::BlockOnError::Join(e) if e.is_panic()));
+}
+
+#[test]
+fn task_id_matches_handle() {
+    let rt = asynk::builder().build().unwrap();
+
+    let jh = rt.spawn(async { asynk::task::id() });
+    let id = jh.id();
+    let other = rt.spawn(async {});
+
+    assert_ne!(id, other.id());
+    assert_eq!(rt.block_on(jh).unwrap().unwrap(), id);
+    assert!(asynk::task::try_id().is_none());
+}
+
+#[test]
+fn builder_sets_name_and_location() {
+    let rt = asynk::builder().build().unwrap();
+
+    let jh = asynk::task::Builder::new()
+        .name("worker")
+        .spawn_on(future::pending::<()>(), rt.handle());
+    let line = line!() - 1;
+
+    let debug = format!("{jh:?}");
+    assert!(debug.contains("\"worker\""), "{debug}");
+    assert!(debug.contains(&format!("{}:{line}", file!())), "{debug}");
+
+    let debug = format!("{:?}", jh.abort_handle());
+    assert!(debug.starts_with("AbortHandle"), "{debug}");
+    assert!(debug.contains("\"worker\""), "{debug}");
+}
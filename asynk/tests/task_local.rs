@@ -0,0 +1,74 @@
+asynk::task_local! {
+    static REQUEST_ID: u64;
+
+    #[inherit]
+    static TENANT: String;
+}
+
+#[test]
+fn value_is_set_inside_scope() {
+    let rt = asynk::builder().build().unwrap();
+
+    let res = rt.block_on(REQUEST_ID.scope(7, async {
+        let before = REQUEST_ID.get();
+        asynk::task::yield_now().await;
+        (before, REQUEST_ID.get())
+    }));
+
+    assert_eq!(res.unwrap(), (7, 7));
+    assert!(REQUEST_ID.try_with(|_| ()).is_err());
+}
+
+#[test]
+fn value_moves_with_task_between_polls() {
+    let rt = asynk::builder().build().unwrap();
+
+    let jh = rt.spawn(REQUEST_ID.scope(1, async {
+        for _ in 0..10 {
+            asynk::task::yield_now().await;
+            assert_eq!(REQUEST_ID.get(), 1);
+        }
+    }));
+
+    let other = rt.spawn(REQUEST_ID.scope(2, async {
+        for _ in 0..10 {
+            asynk::task::yield_now().await;
+            assert_eq!(REQUEST_ID.get(), 2);
+        }
+    }));
+
+    rt.block_on(async {
+        jh.await.unwrap();
+        other.await.unwrap();
+    })
+    .unwrap();
+}
+
+#[test]
+fn inheritable_value_is_cloned_into_children() {
+    let rt = asynk::builder().build().unwrap();
+
+    let res = rt.block_on(TENANT.scope("acme".to_string(), async {
+        let inherited = asynk::spawn(async { TENANT.get() }).await.unwrap();
+
+        // Non-inheritable keys are not set in the children
+        let not_inherited = REQUEST_ID
+            .scope(7, async {
+                asynk::spawn(async { REQUEST_ID.try_with(|_| ()) }).await
+            })
+            .await
+            .unwrap();
+
+        (inherited, not_inherited.is_err())
+    }));
+
+    assert_eq!(res.unwrap(), ("acme".to_string(), true));
+}
+
+#[test]
+fn inheritable_value_is_not_set_outside_scope() {
+    let rt = asynk::builder().build().unwrap();
+
+    let res = rt.block_on(async { asynk::spawn(async { TENANT.try_with(|_| ()) }).await });
+    assert!(res.unwrap().unwrap().is_err());
+}
@@ -0,0 +1,42 @@
+use std::{num::NonZeroUsize, rc::Rc, thread};
+
+#[test]
+fn block_on_all_runs_a_future_per_core() {
+    let rt = asynk::builder()
+        .task_threads(NonZeroUsize::new(2).unwrap())
+        .build_thread_per_core()
+        .unwrap();
+
+    assert_eq!(rt.cores(), 2);
+
+    let outputs = rt
+        .block_on_all(|idx| async move {
+            // Core futures and their local tasks don't have to be `Send`
+            let idx = Rc::new(idx);
+            let local = asynk::spawn_local({
+                let idx = Rc::clone(&idx);
+                async move { *idx * 10 }
+            });
+
+            let spawned = asynk::spawn(async { thread::current().name().map(String::from) });
+
+            (*idx, local.await.unwrap(), spawned.await.unwrap())
+        })
+        .unwrap();
+
+    for (idx, out) in outputs.into_iter().enumerate() {
+        let name = format!("asynk-core-{idx}");
+        assert_eq!(out.unwrap(), (idx, idx * 10, Some(name)));
+    }
+}
+
+#[test]
+fn block_on_all_fails_if_core_thread_is_not_spawned() {
+    let rt = asynk::builder()
+        .task_threads(NonZeroUsize::new(2).unwrap())
+        .thread_stack_size(1 << 62)
+        .build_thread_per_core()
+        .unwrap();
+
+    assert!(rt.block_on_all(|_| async {}).is_err());
+}
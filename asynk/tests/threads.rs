@@ -0,0 +1,126 @@
+use parking_lot::Mutex;
+use std::{
+    hint,
+    num::NonZeroUsize,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    thread,
+    time::Duration,
+};
+
+fn thread_name() -> Option<String> {
+    thread::current().name().map(str::to_owned)
+}
+
+#[test]
+fn threads_are_named_with_prefix() {
+    let rt = asynk::builder()
+        .task_threads(NonZeroUsize::MIN)
+        .thread_name_prefix("app")
+        .build()
+        .unwrap();
+
+    let names = rt.block_on(async {
+        (
+            asynk::spawn(async { thread_name() }).await.unwrap(),
+            asynk::spawn_blocking(thread_name).await.unwrap(),
+            asynk::spawn_compute(thread_name).await.unwrap(),
+        )
+    });
+
+    assert_eq!(
+        names.unwrap(),
+        (
+            Some("app-worker-0".to_owned()),
+            Some("app-blocking-0".to_owned()),
+            Some("app-compute-0".to_owned()),
+        )
+    );
+}
+
+#[test]
+fn start_and_stop_hooks_run_on_every_thread() {
+    let started = Arc::new(Mutex::new(Vec::new()));
+    let stopped = Arc::new(Mutex::new(Vec::new()));
+
+    let rt = asynk::builder()
+        .task_threads(NonZeroUsize::new(2).unwrap())
+        .on_thread_start({
+            let started = Arc::clone(&started);
+            move || started.lock().push(thread_name().unwrap())
+        })
+        .on_thread_stop({
+            let stopped = Arc::clone(&stopped);
+            move || stopped.lock().push(thread_name().unwrap())
+        })
+        .build()
+        .unwrap();
+
+    rt.block_on(rt.spawn_blocking(|| ())).unwrap().unwrap();
+
+    // The runtime waits for its threads on drop
+    drop(rt);
+
+    let expected = ["asynk-blocking-0", "asynk-worker-0", "asynk-worker-1"];
+
+    for names in [started, stopped] {
+        let mut names = names.lock().clone();
+        names.sort();
+        assert_eq!(names, expected);
+    }
+}
+
+#[test]
+fn park_hooks_run_around_waiting() {
+    let parks = Arc::new(AtomicUsize::new(0));
+    let unparks = Arc::new(AtomicUsize::new(0));
+
+    let rt = asynk::builder()
+        .current_thread()
+        .on_thread_park({
+            let parks = Arc::clone(&parks);
+            move || _ = parks.fetch_add(1, Ordering::SeqCst)
+        })
+        .on_thread_unpark({
+            let unparks = Arc::clone(&unparks);
+            move || _ = unparks.fetch_add(1, Ordering::SeqCst)
+        })
+        .build()
+        .unwrap();
+
+    // Nothing is runnable until the timer fires, so the thread waits
+    rt.block_on(futures_timer::Delay::new(Duration::from_millis(10)))
+        .unwrap();
+
+    let parks = parks.load(Ordering::SeqCst);
+    assert!(parks > 0);
+    assert_eq!(unparks.load(Ordering::SeqCst), parks);
+}
+
+/// Use a few megabytes of the stack, which is more than the default stack of
+/// the spawned threads
+#[inline(never)]
+fn use_stack() -> u8 {
+    let buf = [1u8; 4 << 20];
+    hint::black_box(&buf)[buf.len() - 1]
+}
+
+#[test]
+fn threads_have_configured_stack_size() {
+    let rt = asynk::builder()
+        .task_threads(NonZeroUsize::MIN)
+        .thread_stack_size(64 << 20)
+        .build()
+        .unwrap();
+
+    let res = rt.block_on(async {
+        (
+            asynk::spawn(async { use_stack() }).await.unwrap(),
+            asynk::spawn_blocking(use_stack).await.unwrap(),
+        )
+    });
+
+    assert_eq!(res.unwrap(), (1, 1));
+}